@@ -1,5 +1,3 @@
-use std::io::stdout;
-
 use anstyle::{Ansi256Color, Color, RgbColor, Style};
 use anstyle_owo_colors::to_owo_style;
 use owo_colors::OwoColorize;
@@ -7,7 +5,7 @@ use termprofile::{DetectorSettings, TermProfile};
 
 fn main() {
     let color = parse_input();
-    let profile = TermProfile::detect(&stdout(), DetectorSettings::with_query().unwrap());
+    let profile = TermProfile::detect_stdout(DetectorSettings::with_query_or_fallback());
     println!("Detected profile: {profile:?}");
     print!("Adapted: ");
     print_color(profile, color);
@@ -1,8 +1,84 @@
-use std::io::stdout;
+use std::process::ExitCode;
 
 use termprofile::{DetectorSettings, TermProfile};
 
-fn main() {
-    let profile = TermProfile::detect(&stdout(), DetectorSettings::with_query().unwrap());
-    println!("Detected profile: {profile:?}");
+const USAGE: &str = "Usage: detect [--quiet] [--level | --ge <profile>]
+
+<profile> is a level (0-5) or one of notty, nocolor, ansi8, ansi16, ansi256, truecolor";
+
+// --level: print the numeric profile level and use it as the exit code
+// --ge <profile>: exit with 0 if the detected profile is at least <profile>, otherwise 1
+// --quiet: don't print anything, only set the exit code
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let quiet = args.iter().any(|a| a == "--quiet");
+    let level = args.iter().any(|a| a == "--level");
+    let ge = match args.iter().position(|a| a == "--ge") {
+        Some(i) => match args.get(i + 1).and_then(|p| parse_profile(p)) {
+            Some(profile) => Some(profile),
+            None => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        },
+        None => None,
+    };
+
+    let profile = TermProfile::detect_stdout(DetectorSettings::with_query_or_fallback());
+
+    if let Some(min_profile) = ge {
+        if !quiet {
+            println!("Detected profile: {profile:?}");
+        }
+        return if profile >= min_profile {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+    if level {
+        let level = profile_level(profile);
+        if !quiet {
+            println!("{level}");
+        }
+        return ExitCode::from(level);
+    }
+    if !quiet {
+        println!("Detected profile: {profile:?}");
+        println!("{}", profile.describe());
+    }
+    ExitCode::SUCCESS
+}
+
+// Numeric levels, ordered from least to most capable. The index is used for both printing and
+// parsing levels so they stay in sync.
+const LEVELS: [TermProfile; 6] = [
+    TermProfile::NoTty,
+    TermProfile::NoColor,
+    TermProfile::Ansi8,
+    TermProfile::Ansi16,
+    TermProfile::Ansi256,
+    TermProfile::TrueColor,
+];
+
+fn profile_level(profile: TermProfile) -> u8 {
+    LEVELS
+        .iter()
+        .position(|p| *p == profile)
+        .expect("all profiles have a level") as u8
+}
+
+fn parse_profile(profile: &str) -> Option<TermProfile> {
+    if let Ok(level) = profile.parse::<usize>() {
+        return LEVELS.get(level).copied();
+    }
+    match profile.to_ascii_lowercase().as_str() {
+        "notty" => Some(TermProfile::NoTty),
+        "nocolor" => Some(TermProfile::NoColor),
+        "ansi8" => Some(TermProfile::Ansi8),
+        "ansi16" => Some(TermProfile::Ansi16),
+        "ansi256" => Some(TermProfile::Ansi256),
+        "truecolor" => Some(TermProfile::TrueColor),
+        _ => None,
+    }
 }
@@ -1,4 +1,4 @@
-use std::io::{self, stdout};
+use std::io;
 
 use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::style::{Color, Style};
@@ -9,7 +9,7 @@ use termprofile::{DetectorSettings, TermProfile};
 fn main() -> io::Result<()> {
     // NOTE: it's important to detect the profile before initializing the terminal since the
     // detection process may affect the terminal state.
-    let profile = TermProfile::detect(&stdout(), DetectorSettings::with_query()?);
+    let profile = TermProfile::detect_stdout(DetectorSettings::with_query()?);
 
     let terminal = ratatui::init();
     let result = run(terminal, profile);
@@ -0,0 +1,24 @@
+use anstream::ColorChoice;
+
+use crate::TermProfile;
+
+impl From<TermProfile> for ColorChoice {
+    /// Converts the profile into an [`anstream`] color choice.
+    ///
+    /// [`NoTty`](TermProfile::NoTty) and [`NoColor`](TermProfile::NoColor) map to
+    /// [`Never`](ColorChoice::Never). Note that `anstream` strips all escape sequences in this
+    /// case, including text modifiers. All other profiles map to
+    /// [`AlwaysAnsi`](ColorChoice::AlwaysAnsi) since the detected profile already confirms that
+    /// the output supports ANSI escape sequences.
+    fn from(profile: TermProfile) -> Self {
+        if profile < TermProfile::Ansi8 {
+            Self::Never
+        } else {
+            Self::AlwaysAnsi
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./color_choice_test.rs"]
+mod color_choice_test;
@@ -0,0 +1,15 @@
+use anstream::ColorChoice;
+use rstest::rstest;
+
+use crate::TermProfile;
+
+#[rstest]
+#[case(TermProfile::NoTty, ColorChoice::Never)]
+#[case(TermProfile::NoColor, ColorChoice::Never)]
+#[case(TermProfile::Ansi8, ColorChoice::AlwaysAnsi)]
+#[case(TermProfile::Ansi16, ColorChoice::AlwaysAnsi)]
+#[case(TermProfile::Ansi256, ColorChoice::AlwaysAnsi)]
+#[case(TermProfile::TrueColor, ColorChoice::AlwaysAnsi)]
+fn color_choice(#[case] profile: TermProfile, #[case] expected: ColorChoice) {
+    assert_eq!(expected, ColorChoice::from(profile));
+}
@@ -1,4 +1,6 @@
-use anstyle::{Ansi256Color, AnsiColor, Color, RgbColor, Style};
+#[cfg(feature = "convert-anstyle")]
+use anstyle::Style;
+use anstyle::{Ansi256Color, AnsiColor, Color, RgbColor};
 
 /// Represents a color that can be converted to each type of color level.
 pub trait AdaptableColor {
@@ -14,6 +16,35 @@ pub trait AdaptableColor {
     fn from_ansi_16(color: AnsiColor) -> Self;
 }
 
+/// Result of adapting a color with
+/// [`TermProfile::adapt_color_outcome`](crate::TermProfile::adapt_color_outcome).
+///
+/// This can be used to skip re-emitting escape sequences for colors that don't need to change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdaptOutcome<C> {
+    /// The color is supported by the profile and was returned as-is.
+    Unchanged(C),
+    /// The color was converted into a variant supported by the profile.
+    Adapted(C),
+    /// The profile doesn't support color.
+    Removed,
+}
+
+impl<C> AdaptOutcome<C> {
+    /// Returns the resulting color, if any.
+    pub fn into_color(self) -> Option<C> {
+        match self {
+            Self::Unchanged(color) | Self::Adapted(color) => Some(color),
+            Self::Removed => None,
+        }
+    }
+
+    /// Returns true if the color was returned as-is.
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Self::Unchanged(_))
+    }
+}
+
 /// Represents a style that can get and set its color properties.
 pub trait AdaptableStyle: Default {
     /// The color type used for the style properties.
@@ -67,6 +98,7 @@ impl AdaptableColor for Color {
     }
 }
 
+#[cfg(feature = "convert-anstyle")]
 impl AdaptableStyle for Style {
     type Color = Color;
 
@@ -94,3 +126,7 @@ impl AdaptableStyle for Style {
         self.underline_color(color)
     }
 }
+
+#[cfg(test)]
+#[path = "./adapt_test.rs"]
+mod adapt_test;
@@ -0,0 +1,157 @@
+use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor, Style};
+use rstest::rstest;
+
+use super::{AdaptOutcome, AdaptableColor, AdaptableStyle};
+use crate::TermProfile;
+
+fn adapt_generic<S>(profile: TermProfile, style: S) -> S
+where
+    S: AdaptableStyle,
+{
+    profile.adapt_style(style)
+}
+
+#[rstest]
+#[case(Color::Rgb(RgbColor(1, 2, 3)), Some(RgbColor(1, 2, 3)), None, None)]
+#[case(Color::Ansi256(Ansi256Color(100)), None, Some(Ansi256Color(100)), None)]
+#[case(Color::Ansi(AnsiColor::Red), None, None, Some(AnsiColor::Red))]
+fn color_variants(
+    #[case] color: Color,
+    #[case] rgb: Option<RgbColor>,
+    #[case] ansi_256: Option<Ansi256Color>,
+    #[case] ansi_16: Option<AnsiColor>,
+) {
+    assert_eq!(color.as_rgb(), rgb);
+    assert_eq!(color.as_ansi_256(), ansi_256);
+    assert_eq!(color.as_ansi_16(), ansi_16);
+}
+
+#[test]
+fn style_getters() {
+    let style = Style::new()
+        .fg_color(Some(AnsiColor::Red.into()))
+        .bg_color(Some(AnsiColor::Green.into()))
+        .underline_color(Some(AnsiColor::Blue.into()));
+    assert_eq!(
+        AdaptableStyle::get_fg_color(&style),
+        Some(AnsiColor::Red.into())
+    );
+    assert_eq!(
+        AdaptableStyle::get_bg_color(&style),
+        Some(AnsiColor::Green.into())
+    );
+    assert_eq!(
+        AdaptableStyle::get_underline_color(&style),
+        Some(AnsiColor::Blue.into())
+    );
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, Color::Rgb(RgbColor(220, 90, 90)))]
+#[case(TermProfile::Ansi256, Color::Ansi256(Ansi256Color(167)))]
+#[case(TermProfile::Ansi16, Color::Ansi(AnsiColor::Yellow))]
+fn generic_adapt(#[case] profile: TermProfile, #[case] out_color: Color) {
+    let in_color = Color::Rgb(RgbColor(220, 90, 90));
+    let res = adapt_generic(
+        profile,
+        Style::new()
+            .fg_color(Some(in_color))
+            .bg_color(Some(in_color))
+            .underline_color(Some(in_color))
+            .effects(Effects::ITALIC),
+    );
+    assert_eq!(
+        res,
+        Style::new()
+            .fg_color(Some(out_color))
+            .bg_color(Some(out_color))
+            .underline_color(Some(out_color))
+            .effects(Effects::ITALIC),
+    );
+}
+
+#[test]
+fn generic_adapt_no_color() {
+    let color = Color::Rgb(RgbColor(220, 90, 90));
+    let res = adapt_generic(
+        TermProfile::NoColor,
+        Style::new()
+            .fg_color(Some(color))
+            .underline_color(Some(color))
+            .effects(Effects::UNDERLINE),
+    );
+    assert_eq!(res, Style::new().effects(Effects::UNDERLINE));
+}
+
+#[test]
+fn generic_adapt_no_tty() {
+    let color = Color::Rgb(RgbColor(220, 90, 90));
+    let res = adapt_generic(
+        TermProfile::NoTty,
+        Style::new().fg_color(Some(color)).effects(Effects::BOLD),
+    );
+    assert_eq!(res, Style::new());
+}
+
+#[rstest]
+#[case(
+    TermProfile::TrueColor,
+    Color::Rgb(RgbColor(1, 2, 3)),
+    AdaptOutcome::Unchanged(Color::Rgb(RgbColor(1, 2, 3)))
+)]
+#[case(
+    TermProfile::Ansi256,
+    Color::Ansi256(Ansi256Color(100)),
+    AdaptOutcome::Unchanged(Color::Ansi256(Ansi256Color(100)))
+)]
+#[case(
+    TermProfile::Ansi16,
+    Color::Ansi(AnsiColor::Red),
+    AdaptOutcome::Unchanged(Color::Ansi(AnsiColor::Red))
+)]
+#[case(
+    TermProfile::Ansi256,
+    Color::Rgb(RgbColor(255, 0, 0)),
+    AdaptOutcome::Adapted(Color::Ansi256(Ansi256Color(196)))
+)]
+#[case(
+    TermProfile::Ansi16,
+    Color::Ansi256(Ansi256Color(9)),
+    AdaptOutcome::Adapted(Color::Ansi(AnsiColor::BrightRed))
+)]
+#[case(
+    TermProfile::NoColor,
+    Color::Ansi(AnsiColor::Red),
+    AdaptOutcome::Removed
+)]
+fn adapt_outcome(
+    #[case] profile: TermProfile,
+    #[case] color: Color,
+    #[case] expected: AdaptOutcome<Color>,
+) {
+    let outcome = profile.adapt_color_outcome(color);
+    assert_eq!(expected, outcome);
+    assert_eq!(profile.adapt_color(color), outcome.into_color());
+}
+
+#[test]
+fn adapt_style_identity_truecolor() {
+    let style = Style::new()
+        .fg_color(Some(Color::Rgb(RgbColor(1, 2, 3))))
+        .bg_color(Some(Color::Ansi256(Ansi256Color(100))))
+        .underline_color(Some(Color::Ansi(AnsiColor::Red)))
+        .effects(Effects::BOLD);
+    assert_eq!(style, TermProfile::TrueColor.adapt_style(style));
+    for color in [
+        style.get_fg_color(),
+        style.get_bg_color(),
+        style.get_underline_color(),
+    ] {
+        assert!(
+            TermProfile::TrueColor
+                .adapt_color_outcome(color.unwrap())
+                .is_unchanged()
+        );
+    }
+    assert_eq!(Style::new(), TermProfile::NoTty.adapt_style(style));
+}
@@ -60,3 +60,24 @@ where
         self.profile.adapt_color(color)
     }
 }
+
+impl TermProfile {
+    /// Creates a new [`ProfileColor`] bound to this profile.
+    ///
+    /// ```
+    /// use termprofile::TermProfile;
+    /// use termprofile::anstyle::{AnsiColor, Color, RgbColor};
+    ///
+    /// let color = TermProfile::Ansi16
+    ///     .color(Color::Rgb(RgbColor(0, 0, 0)))
+    ///     .ansi_256(16)
+    ///     .ansi_16(AnsiColor::BrightBlack);
+    /// assert_eq!(color.adapt(), Some(AnsiColor::BrightBlack.into()));
+    /// ```
+    pub fn color<C>(&self, default_color: C) -> ProfileColor<C>
+    where
+        C: AdaptableColor + Clone,
+    {
+        ProfileColor::new(default_color, *self)
+    }
+}
@@ -1,8 +1,8 @@
 use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor, Style};
 use rstest::rstest;
 
-use super::ProfileColor;
-use crate::TermProfile;
+use super::{LINUX_CONSOLE_PALETTE, ProfileColor, color_delta, to_ansi256};
+use crate::{DetectorSettings, NoTerminal, Rgb, TermProfile, TermVars};
 
 #[rstest]
 #[case(RgbColor(220, 90, 90), Ansi256Color(167))]
@@ -122,6 +122,16 @@ fn no_change(#[case] profile: TermProfile, #[case] color: Color) {
     assert_eq!(res, color);
 }
 
+#[test]
+fn truecolor_style_unchanged() {
+    let style = Style::new()
+        .fg_color(Some(RgbColor(1, 2, 3).into()))
+        .bg_color(Some(Ansi256Color(100).into()))
+        .underline_color(Some(AnsiColor::Red.into()))
+        .effects(Effects::BOLD);
+    assert_eq!(TermProfile::TrueColor.adapt_style(style), style);
+}
+
 #[test]
 fn profile_color_no_change() {
     let color = ProfileColor::new(Color::Rgb(RgbColor(0, 0, 0)), TermProfile::TrueColor);
@@ -152,3 +162,682 @@ fn profile_color_downsample_priority() {
     let color = ProfileColor::new(Color::Rgb(RgbColor(0, 0, 0)), TermProfile::Ansi16).ansi_256(8);
     assert_eq!(color.adapt(), Some(AnsiColor::BrightBlack.into()));
 }
+
+#[test]
+fn fg_escape() {
+    let escape = TermProfile::Ansi16.fg_escape(Color::Ansi(AnsiColor::Red));
+    assert_eq!(escape, "\x1b[31m");
+}
+
+#[test]
+fn bg_escape() {
+    let escape = TermProfile::Ansi16.bg_escape(Color::Rgb(RgbColor(255, 0, 0)));
+    assert_eq!(escape, "\x1b[101m");
+}
+
+#[rstest]
+#[case(TermProfile::NoTty)]
+#[case(TermProfile::NoColor)]
+fn escape_no_color(#[case] profile: TermProfile) {
+    assert!(profile.fg_escape(Color::Ansi(AnsiColor::Red)).is_empty());
+    assert!(profile.bg_escape(Color::Ansi(AnsiColor::Red)).is_empty());
+}
+
+const DARK_BACKGROUND: Rgb = Rgb {
+    red: 0,
+    green: 0,
+    blue: 0,
+};
+
+const LIGHT_BACKGROUND: Rgb = Rgb {
+    red: 255,
+    green: 255,
+    blue: 255,
+};
+
+#[rstest]
+#[case(RgbColor(0, 0, 0), DARK_BACKGROUND, AnsiColor::Black)]
+#[case(RgbColor(0, 0, 0), LIGHT_BACKGROUND, AnsiColor::Black)]
+#[case(RgbColor(255, 255, 255), DARK_BACKGROUND, AnsiColor::BrightWhite)]
+#[case(RgbColor(255, 255, 255), LIGHT_BACKGROUND, AnsiColor::BrightWhite)]
+#[case(RgbColor(128, 128, 128), DARK_BACKGROUND, AnsiColor::BrightBlack)]
+#[case(RgbColor(128, 128, 128), LIGHT_BACKGROUND, AnsiColor::BrightBlack)]
+#[case(RgbColor(255, 0, 0), LIGHT_BACKGROUND, AnsiColor::BrightRed)]
+#[case(RgbColor(128, 0, 0), DARK_BACKGROUND, AnsiColor::Red)]
+// Nearly halfway between red and bright red
+#[case(RgbColor(192, 0, 0), DARK_BACKGROUND, AnsiColor::BrightRed)]
+#[case(RgbColor(192, 0, 0), LIGHT_BACKGROUND, AnsiColor::Red)]
+fn adapt_with_background(
+    #[case] in_color: RgbColor,
+    #[case] background: Rgb,
+    #[case] out_color: AnsiColor,
+) {
+    let res = TermProfile::Ansi16
+        .adapt_color_with_background(Color::Rgb(in_color), background)
+        .unwrap();
+    assert_eq!(res, Color::Ansi(out_color));
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, Color::Rgb(RgbColor(128, 128, 128)))]
+#[case(TermProfile::Ansi256, Color::Ansi256(Ansi256Color(244)))]
+#[case(TermProfile::Ansi16, Color::Ansi(AnsiColor::BrightBlack))]
+fn adapt_with_background_unchanged(#[case] profile: TermProfile, #[case] color: Color) {
+    let res = profile
+        .adapt_color_with_background(color, LIGHT_BACKGROUND)
+        .unwrap();
+    assert_eq!(res, color);
+}
+
+#[rstest]
+#[case(TermProfile::NoTty, 0, 0)]
+#[case(TermProfile::NoColor, 0, 0)]
+#[case(TermProfile::Ansi16, 2, 8)]
+#[case(TermProfile::Ansi256, 16, 16)]
+#[case(TermProfile::TrueColor, 4, 32)]
+fn swatch_string(#[case] profile: TermProfile, #[case] rows: usize, #[case] row_len: usize) {
+    let swatch = profile.swatch_string();
+    assert_eq!(swatch.lines().count(), rows);
+    for line in swatch.lines() {
+        assert_eq!(line.matches("  ").count(), row_len);
+        assert!(line.ends_with("\x1b[0m"));
+    }
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor)]
+#[case(TermProfile::Ansi256)]
+#[case(TermProfile::Ansi16)]
+fn blend_endpoints(#[case] profile: TermProfile) {
+    let a = Color::Rgb(RgbColor(255, 0, 0));
+    let b = Color::Rgb(RgbColor(0, 0, 255));
+    assert_eq!(profile.blend(a, b, 0.0), profile.adapt_color(a));
+    assert_eq!(profile.blend(a, b, 1.0), profile.adapt_color(b));
+}
+
+#[test]
+fn blend_midpoint() {
+    let a = Color::Rgb(RgbColor(255, 0, 0));
+    let b = Color::Ansi(AnsiColor::BrightBlue);
+    let res = TermProfile::TrueColor.blend(a, b, 0.5);
+    assert_eq!(res, Some(Color::Rgb(RgbColor(128, 0, 128))));
+}
+
+#[test]
+fn blend_no_color() {
+    let a = Color::Rgb(RgbColor(255, 0, 0));
+    let b = Color::Rgb(RgbColor(0, 0, 255));
+    assert_eq!(TermProfile::NoColor.blend(a, b, 0.5), None);
+}
+
+#[rstest]
+#[case(
+    TermProfile::Ansi16,
+    Color::Ansi(AnsiColor::BrightYellow),
+    Color::Ansi(AnsiColor::Yellow)
+)]
+#[case(
+    TermProfile::Ansi256,
+    Color::Ansi256(Ansi256Color(16)),
+    Color::Ansi256(Ansi256Color(167))
+)]
+fn adapt_pair_collision(#[case] profile: TermProfile, #[case] fg: Color, #[case] bg: Color) {
+    let res = profile.adapt_pair(
+        Rgb {
+            red: 220,
+            green: 90,
+            blue: 90,
+        },
+        Rgb {
+            red: 215,
+            green: 95,
+            blue: 95,
+        },
+    );
+    assert_eq!(res, (Some(fg), Some(bg)));
+}
+
+#[test]
+fn adapt_pair_no_collision() {
+    let res = TermProfile::Ansi16.adapt_pair::<Color>(
+        Rgb {
+            red: 255,
+            green: 255,
+            blue: 255,
+        },
+        Rgb {
+            red: 0,
+            green: 0,
+            blue: 0,
+        },
+    );
+    assert_eq!(
+        res,
+        (
+            Some(AnsiColor::BrightWhite.into()),
+            Some(AnsiColor::Black.into())
+        )
+    );
+}
+
+#[rstest]
+#[case(TermProfile::NoTty)]
+#[case(TermProfile::NoColor)]
+#[case(TermProfile::Ansi16)]
+#[case(TermProfile::Ansi256)]
+#[case(TermProfile::TrueColor)]
+fn adapt_style_in_place(#[case] profile: TermProfile) {
+    let style = Style::new()
+        .fg_color(Some(RgbColor(220, 90, 90).into()))
+        .bg_color(Some(Ansi256Color(167).into()))
+        .underline_color(Some(AnsiColor::Red.into()))
+        .effects(Effects::BOLD);
+    let mut in_place = style;
+    profile.adapt_style_in_place(&mut in_place);
+    assert_eq!(in_place, profile.adapt_style(style));
+}
+
+#[test]
+fn write_fg() {
+    let mut out = Vec::new();
+    TermProfile::Ansi256
+        .write_fg(&mut out, Color::Rgb(RgbColor(255, 0, 0)))
+        .unwrap();
+    assert_eq!(out, b"\x1b[38;5;196m");
+}
+
+#[test]
+fn write_bg() {
+    let mut out = Vec::new();
+    TermProfile::Ansi16
+        .write_bg(&mut out, Color::Ansi(AnsiColor::Red))
+        .unwrap();
+    assert_eq!(out, b"\x1b[41m");
+}
+
+#[rstest]
+#[case(TermProfile::NoTty)]
+#[case(TermProfile::NoColor)]
+fn write_no_color(#[case] profile: TermProfile) {
+    let mut out = Vec::new();
+    profile
+        .write_fg(&mut out, Color::Ansi(AnsiColor::Red))
+        .unwrap();
+    profile
+        .write_bg(&mut out, Color::Ansi(AnsiColor::Red))
+        .unwrap();
+    assert!(out.is_empty());
+}
+
+#[rstest]
+#[case(RgbColor(0, 204, 0), AnsiColor::Green, AnsiColor::BrightGreen)]
+#[case(
+    RgbColor(255, 255, 85),
+    AnsiColor::BrightYellow,
+    AnsiColor::BrightYellow
+)]
+#[case(RgbColor(0, 0, 0), AnsiColor::Black, AnsiColor::Black)]
+fn adapt_with_linux_palette(
+    #[case] in_color: RgbColor,
+    #[case] linux_color: AnsiColor,
+    #[case] xterm_color: AnsiColor,
+) {
+    let res = TermProfile::Ansi16
+        .adapt_color_with_palette(Color::Rgb(in_color), &LINUX_CONSOLE_PALETTE)
+        .unwrap();
+    assert_eq!(res, Color::Ansi(linux_color));
+    let res = TermProfile::Ansi16
+        .adapt_color(Color::Rgb(in_color))
+        .unwrap();
+    assert_eq!(res, Color::Ansi(xterm_color));
+}
+
+#[rstest]
+#[case(TermProfile::Ansi256, Color::Ansi256(Ansi256Color(130)))]
+#[case(TermProfile::TrueColor, Color::Rgb(RgbColor(170, 85, 0)))]
+fn adapt_with_palette_other_profiles(#[case] profile: TermProfile, #[case] expected: Color) {
+    let res = profile
+        .adapt_color_with_palette(Color::Rgb(RgbColor(170, 85, 0)), &LINUX_CONSOLE_PALETTE)
+        .unwrap();
+    assert_eq!(res, expected);
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, RgbColor(220, 90, 90), true)]
+#[case(TermProfile::Ansi256, RgbColor(0x5f, 0x87, 0xaf), true)]
+#[case(TermProfile::Ansi256, RgbColor(220, 90, 90), false)]
+#[case(TermProfile::Ansi16, RgbColor(0xff, 0x00, 0x00), true)]
+#[case(TermProfile::Ansi16, RgbColor(0x5f, 0x87, 0xaf), false)]
+fn color_delta_exact(#[case] profile: TermProfile, #[case] color: RgbColor, #[case] exact: bool) {
+    let delta = color_delta(
+        Rgb {
+            red: color.r(),
+            green: color.g(),
+            blue: color.b(),
+        },
+        profile,
+    );
+    assert_eq!(delta == 0, exact);
+}
+
+#[test]
+fn color_delta_ordering() {
+    let color = Rgb {
+        red: 220,
+        green: 90,
+        blue: 90,
+    };
+    assert!(color_delta(color, TermProfile::Ansi16) > color_delta(color, TermProfile::Ansi256));
+    assert_eq!(color_delta(color, TermProfile::NoColor), u32::MAX);
+}
+
+#[rstest]
+#[case(RgbColor(220, 90, 90), Ansi256Color(167))]
+#[case(RgbColor(20, 73, 18), Ansi256Color(22))]
+#[case(RgbColor(255, 0, 0), Ansi256Color(196))]
+#[case(RgbColor(255, 255, 255), Ansi256Color(231))]
+#[case(RgbColor(250, 250, 250), Ansi256Color(231))]
+#[case(RgbColor(0, 0, 0), Ansi256Color(16))]
+fn to_ansi256_matches_profile(#[case] in_color: RgbColor, #[case] out_color: Ansi256Color) {
+    assert_eq!(to_ansi256(&Color::Rgb(in_color)), Some(out_color));
+}
+
+#[rstest]
+#[case(Color::Ansi(AnsiColor::BrightRed), Some(Ansi256Color(9)))]
+#[case(Color::Ansi256(Ansi256Color(100)), Some(Ansi256Color(100)))]
+#[case(Color::Rgb(RgbColor(255, 0, 0)), Some(Ansi256Color(196)))]
+fn to_ansi256_variants(#[case] color: Color, #[case] expected: Option<Ansi256Color>) {
+    assert_eq!(to_ansi256(&color), expected);
+}
+
+#[rstest]
+#[case(
+    TermProfile::TrueColor,
+    Color::Ansi(AnsiColor::BrightRed),
+    Some(Color::Ansi256(Ansi256Color(9)))
+)]
+#[case(
+    TermProfile::Ansi256,
+    Color::Ansi(AnsiColor::Black),
+    Some(Color::Ansi256(Ansi256Color(0)))
+)]
+#[case(
+    TermProfile::Ansi256,
+    Color::Rgb(RgbColor(255, 0, 0)),
+    Some(Color::Ansi256(Ansi256Color(196)))
+)]
+#[case(
+    TermProfile::TrueColor,
+    Color::Rgb(RgbColor(255, 0, 0)),
+    Some(Color::Rgb(RgbColor(255, 0, 0)))
+)]
+#[case(
+    TermProfile::Ansi16,
+    Color::Ansi(AnsiColor::BrightRed),
+    Some(Color::Ansi(AnsiColor::BrightRed))
+)]
+#[case(TermProfile::NoColor, Color::Ansi(AnsiColor::BrightRed), None)]
+fn canonicalize_to_256(
+    #[case] profile: TermProfile,
+    #[case] color: Color,
+    #[case] expected: Option<Color>,
+) {
+    assert_eq!(profile.canonicalize_to_256(color), expected);
+}
+
+#[test]
+fn rgb_to_ansi256_excluding() {
+    let color = RgbColor(220, 90, 90);
+    assert_eq!(167, super::rgb_to_ansi256_excluding(color, &[]));
+
+    // 167 is 0xd75f5f, 203 is 0xff5f5f
+    assert_eq!(203, super::rgb_to_ansi256_excluding(color, &[167]));
+    let all: Vec<u8> = (16..=255).collect();
+    assert_eq!(167, super::rgb_to_ansi256_excluding(color, &all));
+
+    // Maroon is an exact match for index 1, but the theme-dependent colors are skipped
+    let maroon = RgbColor(128, 0, 0);
+    let nearest = super::rgb_to_ansi256(maroon);
+    let fallback = super::rgb_to_ansi256_excluding(maroon, &[nearest]);
+    assert!(fallback >= 16 && fallback != nearest, "{fallback}");
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, vec![16, 16, 239, 242, 246, 249, 231, 231])]
+#[case(TermProfile::Ansi256, vec![16, 16, 239, 242, 246, 249, 231, 231])]
+#[case(TermProfile::Ansi16, vec![0, 0, 8, 8, 8, 7, 15, 15])]
+#[case(TermProfile::Ansi8, vec![0, 0, 0, 0, 0, 7, 7, 7])]
+#[case(TermProfile::NoColor, vec![])]
+fn adapt_scanline(#[case] profile: TermProfile, #[case] expected: Vec<u8>) {
+    let pixels: Vec<_> = [0, 0, 73, 109, 146, 182, 255, 255]
+        .into_iter()
+        .map(|v| RgbColor(v, v, v))
+        .collect();
+    assert_eq!(expected, profile.adapt_scanline(&pixels));
+}
+
+#[rstest]
+#[case(Color::Ansi(AnsiColor::BrightRed), Color::Ansi(AnsiColor::Red))]
+#[case(Color::Ansi(AnsiColor::Blue), Color::Ansi(AnsiColor::Blue))]
+#[case(Color::Ansi256(Ansi256Color(196)), Color::Ansi(AnsiColor::Red))]
+#[case(Color::Rgb(RgbColor(255, 255, 255)), Color::Ansi(AnsiColor::White))]
+fn adapt_ansi8(#[case] color: Color, #[case] expected: Color) {
+    assert_eq!(Some(expected), TermProfile::Ansi8.adapt_color(color));
+}
+
+#[test]
+fn adapt_pair_ansi8_same_color() {
+    let (fg, bg) =
+        TermProfile::Ansi8.adapt_pair::<Color>(Rgb::from((10, 10, 10)), Rgb::from((0, 0, 0)));
+    assert_eq!(Some(Color::Ansi(AnsiColor::White)), fg);
+    assert_eq!(Some(Color::Ansi(AnsiColor::Black)), bg);
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, Color::Rgb(RgbColor(230, 230, 230)))]
+#[case(TermProfile::Ansi256, Color::Ansi256(Ansi256Color(254)))]
+#[case(TermProfile::Ansi16, Color::Ansi(AnsiColor::BrightWhite))]
+fn pick_contrasting_dark_background(#[case] profile: TermProfile, #[case] expected: Color) {
+    let res = profile.pick_contrasting(
+        Rgb::from((20, 20, 20)),
+        Color::Rgb(RgbColor(30, 30, 90)),
+        Color::Rgb(RgbColor(230, 230, 230)),
+    );
+    assert_eq!(Some(expected), res);
+}
+
+#[test]
+fn pick_contrasting_prefers_first() {
+    let res = TermProfile::TrueColor.pick_contrasting(
+        Rgb::from((255, 255, 255)),
+        Color::Rgb(RgbColor(0, 0, 128)),
+        Color::Rgb(RgbColor(0, 0, 0)),
+    );
+    assert_eq!(Some(Color::Rgb(RgbColor(0, 0, 128))), res);
+}
+
+#[test]
+fn pick_contrasting_no_color() {
+    let res = TermProfile::NoColor.pick_contrasting(
+        Rgb::from((0, 0, 0)),
+        Color::Ansi(AnsiColor::Black),
+        Color::Ansi(AnsiColor::White),
+    );
+    assert_eq!(None, res);
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, Some(Color::Rgb(RgbColor(255, 0, 0))))]
+#[case(TermProfile::Ansi16, Some(Color::Ansi(AnsiColor::BrightRed)))]
+#[case(TermProfile::NoColor, None)]
+fn styled(#[case] profile: TermProfile, #[case] expected: Option<Color>) {
+    let style = profile
+        .styled()
+        .fg(RgbColor(255, 0, 0))
+        .italic()
+        .underline()
+        .finish();
+    assert_eq!(
+        Style::new()
+            .fg_color(expected)
+            .effects(Effects::ITALIC | Effects::UNDERLINE),
+        style
+    );
+}
+
+#[test]
+fn palette_from_reported_colors() {
+    let palette =
+        super::palette_from_colors(&[(1, Rgb::from((205, 49, 49))), (20, Rgb::from((1, 2, 3)))]);
+    assert_eq!(RgbColor(205, 49, 49), palette[1]);
+    assert_eq!(super::ansi256_to_rgb(Ansi256Color(2)), palette[2]);
+
+    // The reported red is darker than xterm's default, so it should be matched to red instead
+    // of bright red
+    let color = Color::Rgb(RgbColor(210, 50, 50));
+    assert_eq!(
+        Some(Color::Ansi(AnsiColor::Red)),
+        TermProfile::Ansi16.adapt_color_with_palette(color, &palette)
+    );
+}
+
+#[test]
+fn distinct_steps_ansi16() {
+    let steps: Vec<Color> =
+        TermProfile::Ansi16.distinct_steps(Rgb::from((0, 0, 0)), Rgb::from((255, 255, 255)), 4);
+    assert_eq!(4, steps.len());
+    assert!(steps.windows(2).all(|w| w[0] != w[1]), "{steps:?}");
+    assert_eq!(Color::Ansi(AnsiColor::Black), steps[0]);
+    assert_eq!(Color::Ansi(AnsiColor::BrightWhite), steps[3]);
+}
+
+#[test]
+fn distinct_steps_not_enough_colors() {
+    let steps: Vec<Color> =
+        TermProfile::Ansi16.distinct_steps(Rgb::from((0, 0, 0)), Rgb::from((10, 10, 10)), 3);
+    assert_eq!(vec![Color::Ansi(AnsiColor::Black); 3], steps);
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, 5)]
+#[case(TermProfile::Ansi256, 5)]
+#[case(TermProfile::Ansi16, 1)]
+#[case(TermProfile::NoColor, 0)]
+fn distinct_steps_len(#[case] profile: TermProfile, #[case] n: usize) {
+    let steps: Vec<Color> =
+        profile.distinct_steps(Rgb::from((255, 0, 0)), Rgb::from((0, 0, 255)), n);
+    assert_eq!(n, steps.len());
+    assert!(steps.windows(2).all(|w| w[0] != w[1]), "{steps:?}");
+}
+
+#[test]
+fn distinct_steps_many() {
+    let n = 10_000;
+    let steps: Vec<Color> =
+        TermProfile::TrueColor.distinct_steps(Rgb::from((0, 0, 0)), Rgb::from((255, 255, 255)), n);
+    assert_eq!(n, steps.len());
+    assert_eq!(Color::Rgb(RgbColor(0, 0, 0)), steps[0]);
+    assert_eq!(Color::Rgb(RgbColor(255, 255, 255)), steps[n - 1]);
+}
+
+#[rstest]
+#[case(Color::Rgb(RgbColor(30, 30, 60)), Effects::DIMMED)]
+#[case(Color::Ansi(AnsiColor::Blue), Effects::DIMMED)]
+#[case(Color::Rgb(RgbColor(240, 240, 200)), Effects::BOLD)]
+#[case(Color::Ansi256(Ansi256Color(229)), Effects::BOLD)]
+fn adapt_style_luminance(#[case] fg: Color, #[case] effect: Effects) {
+    let style = Style::new().fg_color(Some(fg)).effects(Effects::ITALIC);
+    let adapted = TermProfile::NoColor.adapt_style_luminance(style);
+    assert_eq!(Style::new().effects(Effects::ITALIC | effect), adapted);
+}
+
+#[rstest]
+#[case(TermProfile::NoTty)]
+#[case(TermProfile::Ansi16)]
+#[case(TermProfile::TrueColor)]
+fn adapt_style_luminance_other_profiles(#[case] profile: TermProfile) {
+    let style = Style::new().fg_color(Some(Color::Rgb(RgbColor(30, 30, 60))));
+    assert_eq!(
+        profile.adapt_style(style),
+        profile.adapt_style_luminance(style)
+    );
+}
+
+#[rstest]
+#[case(AnsiColor::BrightWhite, Effects::BOLD)]
+#[case(AnsiColor::BrightRed, Effects::BOLD)]
+#[case(AnsiColor::Black, Effects::DIMMED)]
+#[case(AnsiColor::Blue, Effects::DIMMED)]
+#[case(AnsiColor::Yellow, Effects::new())]
+#[case(AnsiColor::BrightBlack, Effects::new())]
+fn ansi16_to_intensity(#[case] color: AnsiColor, #[case] expected: Effects) {
+    assert_eq!(expected, super::ansi16_to_intensity(color));
+}
+
+#[test]
+fn adapt_style_luminance_ansi16_mid() {
+    let style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+    assert_eq!(
+        Style::new(),
+        TermProfile::NoColor.adapt_style_luminance(style)
+    );
+}
+
+#[test]
+fn adapt_style_luminance_no_fg() {
+    let style = Style::new().bg_color(Some(Color::Rgb(RgbColor(255, 255, 255))));
+    assert_eq!(
+        Style::new(),
+        TermProfile::NoColor.adapt_style_luminance(style)
+    );
+}
+
+#[rstest]
+#[case(Rgb::from((255, 0, 0)), vec![TermProfile::Ansi8])]
+#[case(Rgb::from((0, 0, 0)), vec![])]
+#[case(Rgb::from((95, 135, 175)), vec![TermProfile::Ansi8, TermProfile::Ansi16])]
+#[case(
+    Rgb::from((100, 140, 170)),
+    vec![TermProfile::Ansi8, TermProfile::Ansi16, TermProfile::Ansi256]
+)]
+fn color_breakpoints(#[case] color: Rgb, #[case] expected: Vec<TermProfile>) {
+    assert_eq!(expected, super::color_breakpoints(color));
+}
+
+#[test]
+fn ansi256_palette() {
+    let palette = super::ansi256_palette();
+    assert_eq!(RgbColor(0x00, 0x00, 0x00), palette[16]);
+    assert_eq!(RgbColor(0xff, 0xff, 0xff), palette[231]);
+    for (i, color) in palette.iter().enumerate() {
+        assert_eq!(super::ansi256_to_rgb(Ansi256Color(i as u8)), *color);
+    }
+}
+
+#[test]
+fn adapt_gradient_monotonic() {
+    let ramp: Vec<_> = (0..64u8)
+        .map(|i| Color::Rgb(RgbColor(i * 4, i * 3, 160)))
+        .collect();
+    let luminance = |color: &Option<Color>| {
+        super::relative_luminance(super::to_rgb(color.as_ref().unwrap()).unwrap())
+    };
+    let is_monotonic = |colors: &[Option<Color>]| {
+        colors
+            .windows(2)
+            .all(|w| luminance(&w[0]) <= luminance(&w[1]))
+    };
+
+    let input: Vec<_> = ramp.iter().map(|c| Some(*c)).collect();
+    assert!(is_monotonic(&input));
+
+    let plain: Vec<_> = ramp
+        .iter()
+        .map(|c| TermProfile::Ansi16.adapt_color(*c))
+        .collect();
+    assert!(!is_monotonic(&plain));
+
+    let adapted = TermProfile::Ansi16.adapt_gradient(&ramp);
+    assert_eq!(ramp.len(), adapted.len());
+    assert!(is_monotonic(&adapted));
+    let reversed: Vec<_> = ramp.iter().rev().copied().collect();
+    let mut adapted = TermProfile::Ansi16.adapt_gradient(&reversed);
+    adapted.reverse();
+    assert!(is_monotonic(&adapted));
+}
+
+#[test]
+fn adapt_gradient_not_monotonic() {
+    let colors = [
+        Color::Rgb(RgbColor(0, 0, 0)),
+        Color::Rgb(RgbColor(255, 255, 255)),
+        Color::Rgb(RgbColor(0, 0, 0)),
+    ];
+    let expected: Vec<_> = colors
+        .iter()
+        .map(|c| TermProfile::Ansi16.adapt_color(*c))
+        .collect();
+    assert_eq!(expected, TermProfile::Ansi16.adapt_gradient(&colors));
+}
+
+#[rstest]
+#[case(Color::Rgb(RgbColor(255, 0, 0)), Some("red"))]
+#[case(Color::Rgb(RgbColor(140, 0, 0)), Some("darkred"))]
+#[case(Color::Rgb(RgbColor(250, 250, 250)), Some("snow"))]
+#[case(Color::Ansi(AnsiColor::Black), Some("black"))]
+#[case(Color::Ansi256(Ansi256Color(21)), Some("blue"))]
+fn color_name(#[case] color: Color, #[case] expected: Option<&str>) {
+    assert_eq!(expected, super::color_name(&color));
+}
+
+fn term_vars(vars: &[(&str, &str)]) -> TermVars {
+    TermVars::from_source(
+        &std::collections::HashMap::from_iter(vars.iter().copied()),
+        &NoTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false),
+    )
+}
+
+#[rstest]
+#[case(&[("TERM_PROGRAM", "Apple_Terminal")], Effects::STRIKETHROUGH, false)]
+#[case(&[("TERM_PROGRAM", "Apple_Terminal")], Effects::BOLD, true)]
+#[case(&[("TERM_PROGRAM", "Apple_Terminal")], Effects::BOLD | Effects::STRIKETHROUGH, false)]
+#[case(&[("TERM", "alacritty")], Effects::BLINK, false)]
+#[case(&[("TERM", "linux")], Effects::ITALIC, false)]
+#[case(&[("TERM", "xterm-256color")], Effects::STRIKETHROUGH, true)]
+#[case(&[("TERM", "xterm-256color")], Effects::new(), true)]
+fn supports_effect(#[case] vars: &[(&str, &str)], #[case] effect: Effects, #[case] expected: bool) {
+    assert_eq!(expected, term_vars(vars).supports_effect(effect));
+}
+
+#[test]
+fn strip_unsupported_effects() {
+    let vars = term_vars(&[("TERM_PROGRAM", "Apple_Terminal")]);
+    let style = Style::new().effects(Effects::BOLD | Effects::STRIKETHROUGH);
+    assert_eq!(
+        Style::new().effects(Effects::BOLD),
+        vars.strip_unsupported_effects(style)
+    );
+}
+
+#[test]
+fn rgb_to_ansi256_linear() {
+    // A dark indigo loses its hue with the default conversion
+    let color = RgbColor(24, 0, 56);
+    assert_eq!(234, super::rgb_to_ansi256(color));
+    assert_eq!(17, super::rgb_to_ansi256_linear(color));
+
+    for index in 16..=255 {
+        assert_eq!(
+            index,
+            super::rgb_to_ansi256_linear(super::ansi256_to_rgb(Ansi256Color(index)))
+        );
+    }
+}
+
+#[rstest]
+#[case(
+    TermProfile::Ansi256,
+    Color::Rgb(RgbColor(220, 90, 90)),
+    Some((Color::Ansi256(Ansi256Color(167)), RgbColor(0xd7, 0x5f, 0x5f)))
+)]
+#[case(
+    TermProfile::Ansi16,
+    Color::Ansi256(Ansi256Color(196)),
+    Some((Color::Ansi(AnsiColor::BrightRed), RgbColor(0xff, 0x00, 0x00)))
+)]
+#[case(
+    TermProfile::TrueColor,
+    Color::Rgb(RgbColor(1, 2, 3)),
+    Some((Color::Rgb(RgbColor(1, 2, 3)), RgbColor(1, 2, 3)))
+)]
+#[case(TermProfile::NoColor, Color::Rgb(RgbColor(1, 2, 3)), None)]
+fn adapt_color_resolved(
+    #[case] profile: TermProfile,
+    #[case] color: Color,
+    #[case] expected: Option<(Color, RgbColor)>,
+) {
+    assert_eq!(expected, profile.adapt_color_resolved(color));
+}
@@ -0,0 +1,153 @@
+use anstyle::RgbColor;
+
+// Named colors from https://www.w3.org/TR/css-color-4/#named-colors
+pub(crate) const CSS_COLORS: [(&str, RgbColor); 148] = [
+    ("aliceblue", RgbColor(0xf0, 0xf8, 0xff)),
+    ("antiquewhite", RgbColor(0xfa, 0xeb, 0xd7)),
+    ("aqua", RgbColor(0x00, 0xff, 0xff)),
+    ("aquamarine", RgbColor(0x7f, 0xff, 0xd4)),
+    ("azure", RgbColor(0xf0, 0xff, 0xff)),
+    ("beige", RgbColor(0xf5, 0xf5, 0xdc)),
+    ("bisque", RgbColor(0xff, 0xe4, 0xc4)),
+    ("black", RgbColor(0x00, 0x00, 0x00)),
+    ("blanchedalmond", RgbColor(0xff, 0xeb, 0xcd)),
+    ("blue", RgbColor(0x00, 0x00, 0xff)),
+    ("blueviolet", RgbColor(0x8a, 0x2b, 0xe2)),
+    ("brown", RgbColor(0xa5, 0x2a, 0x2a)),
+    ("burlywood", RgbColor(0xde, 0xb8, 0x87)),
+    ("cadetblue", RgbColor(0x5f, 0x9e, 0xa0)),
+    ("chartreuse", RgbColor(0x7f, 0xff, 0x00)),
+    ("chocolate", RgbColor(0xd2, 0x69, 0x1e)),
+    ("coral", RgbColor(0xff, 0x7f, 0x50)),
+    ("cornflowerblue", RgbColor(0x64, 0x95, 0xed)),
+    ("cornsilk", RgbColor(0xff, 0xf8, 0xdc)),
+    ("crimson", RgbColor(0xdc, 0x14, 0x3c)),
+    ("cyan", RgbColor(0x00, 0xff, 0xff)),
+    ("darkblue", RgbColor(0x00, 0x00, 0x8b)),
+    ("darkcyan", RgbColor(0x00, 0x8b, 0x8b)),
+    ("darkgoldenrod", RgbColor(0xb8, 0x86, 0x0b)),
+    ("darkgray", RgbColor(0xa9, 0xa9, 0xa9)),
+    ("darkgreen", RgbColor(0x00, 0x64, 0x00)),
+    ("darkgrey", RgbColor(0xa9, 0xa9, 0xa9)),
+    ("darkkhaki", RgbColor(0xbd, 0xb7, 0x6b)),
+    ("darkmagenta", RgbColor(0x8b, 0x00, 0x8b)),
+    ("darkolivegreen", RgbColor(0x55, 0x6b, 0x2f)),
+    ("darkorange", RgbColor(0xff, 0x8c, 0x00)),
+    ("darkorchid", RgbColor(0x99, 0x32, 0xcc)),
+    ("darkred", RgbColor(0x8b, 0x00, 0x00)),
+    ("darksalmon", RgbColor(0xe9, 0x96, 0x7a)),
+    ("darkseagreen", RgbColor(0x8f, 0xbc, 0x8f)),
+    ("darkslateblue", RgbColor(0x48, 0x3d, 0x8b)),
+    ("darkslategray", RgbColor(0x2f, 0x4f, 0x4f)),
+    ("darkslategrey", RgbColor(0x2f, 0x4f, 0x4f)),
+    ("darkturquoise", RgbColor(0x00, 0xce, 0xd1)),
+    ("darkviolet", RgbColor(0x94, 0x00, 0xd3)),
+    ("deeppink", RgbColor(0xff, 0x14, 0x93)),
+    ("deepskyblue", RgbColor(0x00, 0xbf, 0xff)),
+    ("dimgray", RgbColor(0x69, 0x69, 0x69)),
+    ("dimgrey", RgbColor(0x69, 0x69, 0x69)),
+    ("dodgerblue", RgbColor(0x1e, 0x90, 0xff)),
+    ("firebrick", RgbColor(0xb2, 0x22, 0x22)),
+    ("floralwhite", RgbColor(0xff, 0xfa, 0xf0)),
+    ("forestgreen", RgbColor(0x22, 0x8b, 0x22)),
+    ("fuchsia", RgbColor(0xff, 0x00, 0xff)),
+    ("gainsboro", RgbColor(0xdc, 0xdc, 0xdc)),
+    ("ghostwhite", RgbColor(0xf8, 0xf8, 0xff)),
+    ("gold", RgbColor(0xff, 0xd7, 0x00)),
+    ("goldenrod", RgbColor(0xda, 0xa5, 0x20)),
+    ("gray", RgbColor(0x80, 0x80, 0x80)),
+    ("green", RgbColor(0x00, 0x80, 0x00)),
+    ("greenyellow", RgbColor(0xad, 0xff, 0x2f)),
+    ("grey", RgbColor(0x80, 0x80, 0x80)),
+    ("honeydew", RgbColor(0xf0, 0xff, 0xf0)),
+    ("hotpink", RgbColor(0xff, 0x69, 0xb4)),
+    ("indianred", RgbColor(0xcd, 0x5c, 0x5c)),
+    ("indigo", RgbColor(0x4b, 0x00, 0x82)),
+    ("ivory", RgbColor(0xff, 0xff, 0xf0)),
+    ("khaki", RgbColor(0xf0, 0xe6, 0x8c)),
+    ("lavender", RgbColor(0xe6, 0xe6, 0xfa)),
+    ("lavenderblush", RgbColor(0xff, 0xf0, 0xf5)),
+    ("lawngreen", RgbColor(0x7c, 0xfc, 0x00)),
+    ("lemonchiffon", RgbColor(0xff, 0xfa, 0xcd)),
+    ("lightblue", RgbColor(0xad, 0xd8, 0xe6)),
+    ("lightcoral", RgbColor(0xf0, 0x80, 0x80)),
+    ("lightcyan", RgbColor(0xe0, 0xff, 0xff)),
+    ("lightgoldenrodyellow", RgbColor(0xfa, 0xfa, 0xd2)),
+    ("lightgray", RgbColor(0xd3, 0xd3, 0xd3)),
+    ("lightgreen", RgbColor(0x90, 0xee, 0x90)),
+    ("lightgrey", RgbColor(0xd3, 0xd3, 0xd3)),
+    ("lightpink", RgbColor(0xff, 0xb6, 0xc1)),
+    ("lightsalmon", RgbColor(0xff, 0xa0, 0x7a)),
+    ("lightseagreen", RgbColor(0x20, 0xb2, 0xaa)),
+    ("lightskyblue", RgbColor(0x87, 0xce, 0xfa)),
+    ("lightslategray", RgbColor(0x77, 0x88, 0x99)),
+    ("lightslategrey", RgbColor(0x77, 0x88, 0x99)),
+    ("lightsteelblue", RgbColor(0xb0, 0xc4, 0xde)),
+    ("lightyellow", RgbColor(0xff, 0xff, 0xe0)),
+    ("lime", RgbColor(0x00, 0xff, 0x00)),
+    ("limegreen", RgbColor(0x32, 0xcd, 0x32)),
+    ("linen", RgbColor(0xfa, 0xf0, 0xe6)),
+    ("magenta", RgbColor(0xff, 0x00, 0xff)),
+    ("maroon", RgbColor(0x80, 0x00, 0x00)),
+    ("mediumaquamarine", RgbColor(0x66, 0xcd, 0xaa)),
+    ("mediumblue", RgbColor(0x00, 0x00, 0xcd)),
+    ("mediumorchid", RgbColor(0xba, 0x55, 0xd3)),
+    ("mediumpurple", RgbColor(0x93, 0x70, 0xdb)),
+    ("mediumseagreen", RgbColor(0x3c, 0xb3, 0x71)),
+    ("mediumslateblue", RgbColor(0x7b, 0x68, 0xee)),
+    ("mediumspringgreen", RgbColor(0x00, 0xfa, 0x9a)),
+    ("mediumturquoise", RgbColor(0x48, 0xd1, 0xcc)),
+    ("mediumvioletred", RgbColor(0xc7, 0x15, 0x85)),
+    ("midnightblue", RgbColor(0x19, 0x19, 0x70)),
+    ("mintcream", RgbColor(0xf5, 0xff, 0xfa)),
+    ("mistyrose", RgbColor(0xff, 0xe4, 0xe1)),
+    ("moccasin", RgbColor(0xff, 0xe4, 0xb5)),
+    ("navajowhite", RgbColor(0xff, 0xde, 0xad)),
+    ("navy", RgbColor(0x00, 0x00, 0x80)),
+    ("oldlace", RgbColor(0xfd, 0xf5, 0xe6)),
+    ("olive", RgbColor(0x80, 0x80, 0x00)),
+    ("olivedrab", RgbColor(0x6b, 0x8e, 0x23)),
+    ("orange", RgbColor(0xff, 0xa5, 0x00)),
+    ("orangered", RgbColor(0xff, 0x45, 0x00)),
+    ("orchid", RgbColor(0xda, 0x70, 0xd6)),
+    ("palegoldenrod", RgbColor(0xee, 0xe8, 0xaa)),
+    ("palegreen", RgbColor(0x98, 0xfb, 0x98)),
+    ("paleturquoise", RgbColor(0xaf, 0xee, 0xee)),
+    ("palevioletred", RgbColor(0xdb, 0x70, 0x93)),
+    ("papayawhip", RgbColor(0xff, 0xef, 0xd5)),
+    ("peachpuff", RgbColor(0xff, 0xda, 0xb9)),
+    ("peru", RgbColor(0xcd, 0x85, 0x3f)),
+    ("pink", RgbColor(0xff, 0xc0, 0xcb)),
+    ("plum", RgbColor(0xdd, 0xa0, 0xdd)),
+    ("powderblue", RgbColor(0xb0, 0xe0, 0xe6)),
+    ("purple", RgbColor(0x80, 0x00, 0x80)),
+    ("rebeccapurple", RgbColor(0x66, 0x33, 0x99)),
+    ("red", RgbColor(0xff, 0x00, 0x00)),
+    ("rosybrown", RgbColor(0xbc, 0x8f, 0x8f)),
+    ("royalblue", RgbColor(0x41, 0x69, 0xe1)),
+    ("saddlebrown", RgbColor(0x8b, 0x45, 0x13)),
+    ("salmon", RgbColor(0xfa, 0x80, 0x72)),
+    ("sandybrown", RgbColor(0xf4, 0xa4, 0x60)),
+    ("seagreen", RgbColor(0x2e, 0x8b, 0x57)),
+    ("seashell", RgbColor(0xff, 0xf5, 0xee)),
+    ("sienna", RgbColor(0xa0, 0x52, 0x2d)),
+    ("silver", RgbColor(0xc0, 0xc0, 0xc0)),
+    ("skyblue", RgbColor(0x87, 0xce, 0xeb)),
+    ("slateblue", RgbColor(0x6a, 0x5a, 0xcd)),
+    ("slategray", RgbColor(0x70, 0x80, 0x90)),
+    ("slategrey", RgbColor(0x70, 0x80, 0x90)),
+    ("snow", RgbColor(0xff, 0xfa, 0xfa)),
+    ("springgreen", RgbColor(0x00, 0xff, 0x7f)),
+    ("steelblue", RgbColor(0x46, 0x82, 0xb4)),
+    ("tan", RgbColor(0xd2, 0xb4, 0x8c)),
+    ("teal", RgbColor(0x00, 0x80, 0x80)),
+    ("thistle", RgbColor(0xd8, 0xbf, 0xd8)),
+    ("tomato", RgbColor(0xff, 0x63, 0x47)),
+    ("turquoise", RgbColor(0x40, 0xe0, 0xd0)),
+    ("violet", RgbColor(0xee, 0x82, 0xee)),
+    ("wheat", RgbColor(0xf5, 0xde, 0xb3)),
+    ("white", RgbColor(0xff, 0xff, 0xff)),
+    ("whitesmoke", RgbColor(0xf5, 0xf5, 0xf5)),
+    ("yellow", RgbColor(0xff, 0xff, 0x00)),
+    ("yellowgreen", RgbColor(0x9a, 0xcd, 0x32)),
+];
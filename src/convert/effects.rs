@@ -0,0 +1,47 @@
+use anstyle::Effects;
+#[cfg(feature = "convert-anstyle")]
+use anstyle::Style;
+
+use crate::{TermVars, prefix_or_equal};
+
+// Effects that are ignored by well-known terminals. This is intentionally conservative, terminals
+// that aren't listed are assumed to support every effect.
+const UNSUPPORTED_BY_TERM_PROGRAM: [(&str, Effects); 1] =
+    [("apple_terminal", Effects::STRIKETHROUGH)];
+const UNSUPPORTED_BY_TERM: [(&str, Effects); 2] = [
+    ("alacritty", Effects::BLINK),
+    ("linux", Effects::ITALIC.insert(Effects::STRIKETHROUGH)),
+];
+
+impl TermVars {
+    /// Returns the text effects that the terminal is known to ignore, based on the terminal's
+    /// name from `TERM_PROGRAM` or `TERM`. Terminals that aren't recognized are assumed to
+    /// support every effect.
+    pub fn unsupported_effects(&self) -> Effects {
+        let (term_program, _) = self.meta.term_program();
+        let term = self.meta.term.value();
+        let by_program = UNSUPPORTED_BY_TERM_PROGRAM
+            .iter()
+            .filter(|(name, _)| term_program == *name);
+        let by_term = UNSUPPORTED_BY_TERM
+            .iter()
+            .filter(|(name, _)| prefix_or_equal(&term, name));
+        by_program
+            .chain(by_term)
+            .fold(Effects::new(), |acc, (_, effects)| acc | *effects)
+    }
+
+    /// Returns true if the terminal is expected to render all of the given effects. See
+    /// [`unsupported_effects`](Self::unsupported_effects) for details.
+    pub fn supports_effect(&self, effect: Effects) -> bool {
+        effect.remove(self.unsupported_effects()) == effect
+    }
+
+    /// Removes any effects from the style that the terminal is known to ignore. This can be
+    /// combined with [`TermProfile::adapt_style`](crate::TermProfile::adapt_style) to adapt
+    /// both colors and effects.
+    #[cfg(feature = "convert-anstyle")]
+    pub fn strip_unsupported_effects(&self, style: Style) -> Style {
+        style.effects(style.get_effects().remove(self.unsupported_effects()))
+    }
+}
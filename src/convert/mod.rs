@@ -2,52 +2,411 @@ mod adapt;
 mod ansi_256_to_16;
 mod ansi_256_to_rgb;
 mod color;
-#[cfg(feature = "ratatui")]
+mod css_colors;
+mod effects;
+mod palette;
+#[cfg(feature = "convert-ratatui")]
 mod ratatui;
+#[cfg(feature = "convert-anstyle")]
+mod style;
+mod writer;
+
+use std::collections::HashMap;
+use std::io;
 
 pub use adapt::*;
 use ansi_256_to_16::ANSI_256_TO_16;
 use ansi_256_to_rgb::ANSI_256_TO_RGB;
-use anstyle::{Ansi256Color, AnsiColor, RgbColor};
+#[cfg(feature = "convert-anstyle")]
+use anstyle::Style;
+use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor};
 pub use color::*;
-use palette::Srgb;
+use css_colors::CSS_COLORS;
+pub use palette::*;
+#[cfg(feature = "convert-anstyle")]
+pub use style::*;
+pub use writer::*;
+
+use crate::{Rgb, TermProfile};
 
-use crate::TermProfile;
+const SWATCH_ROW_LEN: usize = 16;
+const GRADIENT_LEN: u16 = 32;
 
 impl TermProfile {
     /// Adapts the color into its nearest compatible variant.
+    ///
+    /// To render at a different level than what was detected, such as when the user forces a
+    /// specific color level with a command line flag even though the output isn't a terminal,
+    /// call this on the profile being rendered to instead.
+    ///
+    /// ```
+    /// use anstyle::{Ansi256Color, Color, RgbColor};
+    /// use termprofile::TermProfile;
+    ///
+    /// let detected = TermProfile::NoTty;
+    /// let color = Color::Rgb(RgbColor(255, 0, 0));
+    /// assert_eq!(None, detected.adapt_color(color));
+    /// assert_eq!(
+    ///     Some(Color::Ansi256(Ansi256Color(196))),
+    ///     TermProfile::Ansi256.adapt_color(color)
+    /// );
+    /// ```
     pub fn adapt_color<C>(&self, color: C) -> Option<C>
     where
         C: AdaptableColor,
     {
-        if *self < Self::Ansi16 {
-            return None;
+        self.adapt_color_outcome(color).into_color()
+    }
+
+    /// Adapts the color into its nearest compatible variant and returns it along with the RGB
+    /// value that the terminal is expected to display for it. Indexed colors are resolved using
+    /// the default xterm palette, so the actual color may differ if the terminal uses a custom
+    /// theme.
+    ///
+    /// Returns [`None`] if the profile doesn't support color or if the adapted color has no RGB
+    /// equivalent (such as a reset color).
+    pub fn adapt_color_resolved<C>(&self, color: C) -> Option<(C, RgbColor)>
+    where
+        C: AdaptableColor,
+    {
+        let adapted = self.adapt_color(color)?;
+        let rgb = to_rgb(&adapted)?;
+        Some((adapted, rgb))
+    }
+
+    /// Adapts the color into its nearest compatible variant, returning whether the color needed
+    /// to be changed.
+    pub fn adapt_color_outcome<C>(&self, color: C) -> AdaptOutcome<C>
+    where
+        C: AdaptableColor,
+    {
+        if *self < Self::Ansi8 {
+            return AdaptOutcome::Removed;
         }
-        if color.as_ansi_16().is_some() {
-            Some(color)
+        if let Some(ansi_16) = color.as_ansi_16() {
+            if *self == Self::Ansi8 && ansi_16.is_bright() {
+                AdaptOutcome::Adapted(C::from_ansi_16(ansi_16.bright(false)))
+            } else {
+                AdaptOutcome::Unchanged(color)
+            }
         } else if let Some(index) = color.as_ansi_256() {
             if *self >= Self::Ansi256 {
-                Some(color)
+                AdaptOutcome::Unchanged(color)
             } else {
-                Some(C::from_ansi_16(ansi256_to_ansi16(index.0)))
+                AdaptOutcome::Adapted(C::from_ansi_16(
+                    self.clamp_ansi16(ansi256_to_ansi16(index.0)),
+                ))
             }
         } else if let Some(rgb_color) = color.as_rgb() {
             if *self == Self::TrueColor {
-                Some(color)
+                AdaptOutcome::Unchanged(color)
             } else {
                 let ansi256_index = rgb_to_ansi256(rgb_color);
                 if *self == Self::Ansi256 {
-                    Some(C::from_ansi_256(ansi256_index.into()))
+                    AdaptOutcome::Adapted(C::from_ansi_256(ansi256_index.into()))
                 } else {
-                    Some(C::from_ansi_16(ansi256_to_ansi16(ansi256_index)))
+                    AdaptOutcome::Adapted(C::from_ansi_16(
+                        self.clamp_ansi16(ansi256_to_ansi16(ansi256_index)),
+                    ))
                 }
             }
         } else {
-            Some(color)
+            AdaptOutcome::Unchanged(color)
+        }
+    }
+
+    // Bright colors aren't available with 8 colors, so they're replaced with their normal variant
+    fn clamp_ansi16(&self, color: AnsiColor) -> AnsiColor {
+        if *self == Self::Ansi8 {
+            color.bright(false)
+        } else {
+            color
         }
     }
 
+    /// Adapts the color like [`adapt_color`](Self::adapt_color), but also converts named 16
+    /// color values into their equivalent ANSI 256 index (0-15) if the profile supports 256
+    /// colors. This doesn't change the color's appearance, but it's useful for normalizing
+    /// palettes that mix both representations.
+    pub fn canonicalize_to_256<C>(&self, color: C) -> Option<C>
+    where
+        C: AdaptableColor,
+    {
+        if *self >= Self::Ansi256
+            && let Some(ansi) = color.as_ansi_16()
+        {
+            return Some(C::from_ansi_256(ansi.into()));
+        }
+        self.adapt_color(color)
+    }
+
+    /// Adapts the color into its nearest compatible variant, taking the terminal's background
+    /// color into account.
+    ///
+    /// When downsampling to 16 colors, the normal and bright variants of the nearest color are
+    /// compared against the original color and the closer one is chosen. If both are about equally
+    /// close, the bright variant is preferred on dark backgrounds and the normal variant is
+    /// preferred on light backgrounds.
+    pub fn adapt_color_with_background<C>(&self, color: C, background: Rgb) -> Option<C>
+    where
+        C: AdaptableColor,
+    {
+        let is_ansi_16 = color.as_ansi_16().is_some();
+        let rgb = to_rgb(&color);
+        let adapted = self.adapt_color(color)?;
+        if *self != Self::Ansi16 || is_ansi_16 {
+            return Some(adapted);
+        }
+        let (Some(rgb), Some(ansi_16)) = (rgb, adapted.as_ansi_16()) else {
+            return Some(adapted);
+        };
+        let distance = |bright: bool| {
+            distance_squared(
+                rgb,
+                ansi256_to_rgb(Ansi256Color::from(ansi_16.bright(bright))),
+            )
+        };
+        let (normal, bright) = (distance(false), distance(true));
+        // Treat distances within 10% of each other as a tie
+        let bright = if normal.abs_diff(bright) * 10 <= normal.max(bright) {
+            !is_light(background)
+        } else {
+            bright < normal
+        };
+        Some(C::from_ansi_16(ansi_16.bright(bright)))
+    }
+
+    /// Adapts the color into its nearest compatible variant, using the given palette to choose
+    /// the nearest color when converting to 16 colors. The palette should contain the RGB
+    /// values of the 16 ANSI colors in order. See [`LINUX_CONSOLE_PALETTE`].
+    ///
+    /// Conversions to other profiles are the same as [`adapt_color`](Self::adapt_color).
+    pub fn adapt_color_with_palette<C>(&self, color: C, palette: &[RgbColor; 16]) -> Option<C>
+    where
+        C: AdaptableColor,
+    {
+        if *self != Self::Ansi16 || color.as_ansi_16().is_some() {
+            return self.adapt_color(color);
+        }
+        let Some(rgb) = to_rgb(&color) else {
+            return self.adapt_color(color);
+        };
+        let index = palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| distance_squared(rgb, **p))
+            .map(|(i, _)| i as u8)
+            .unwrap_or_default();
+        Some(C::from_ansi_16(ansi256_to_ansi16(index)))
+    }
+
+    /// Linearly interpolates between two colors in RGB space and adapts the result. `t` is
+    /// clamped between `0.0` and `1.0`, where `0.0` returns `a` and `1.0` returns `b`.
+    ///
+    /// Returns [`None`] if either color doesn't have an RGB representation or if the profile
+    /// doesn't support colors.
+    pub fn blend<C>(&self, a: C, b: C, t: f32) -> Option<C>
+    where
+        C: AdaptableColor + From<(u8, u8, u8)>,
+    {
+        let a = to_rgb(&a)?;
+        let b = to_rgb(&b)?;
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        let blended = (lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()));
+        self.adapt_color(C::from(blended))
+    }
+
+    /// Returns `n` colors stepping from `start` to `end` that are distinct after adaptation
+    /// where possible. This avoids banding in gradients at lower profiles, where many
+    /// intermediate colors collapse into the same palette entry.
+    ///
+    /// If the profile can't display `n` distinct colors between `start` and `end`, the available
+    /// colors are spread evenly across the steps. Returns an empty [`Vec`] if the profile doesn't
+    /// support colors.
+    pub fn distinct_steps<C>(&self, start: Rgb, end: Rgb, n: usize) -> Vec<C>
+    where
+        C: AdaptableColor + From<(u8, u8, u8)> + PartialEq + Clone,
+    {
+        // Sample enough points that every palette entry along the gradient is likely to be found
+        const SAMPLES_PER_STEP: usize = 16;
+        // A gradient can't contain more than 256 distinct colors since each channel only has 256
+        // values, so sampling beyond this can't find any more palette entries
+        const MAX_SAMPLES: usize = 256 * SAMPLES_PER_STEP;
+        if n == 0 || *self < Self::Ansi8 {
+            return Vec::new();
+        }
+        let samples = n.saturating_mul(SAMPLES_PER_STEP).clamp(2, MAX_SAMPLES);
+        let mut bands: Vec<C> = Vec::new();
+        for i in 0..samples {
+            let t = i as f32 / (samples - 1) as f32;
+            let color = self.blend(
+                C::from((start.red, start.green, start.blue)),
+                C::from((end.red, end.green, end.blue)),
+                t,
+            );
+            if let Some(color) = color
+                && bands.last() != Some(&color)
+            {
+                bands.push(color);
+            }
+        }
+        if bands.is_empty() {
+            return Vec::new();
+        }
+        (0..n)
+            .map(|i| {
+                let index = if n == 1 {
+                    0
+                } else {
+                    i * (bands.len() - 1) / (n - 1)
+                };
+                bands[index].clone()
+            })
+            .collect()
+    }
+
+    /// Adapts a foreground and background color pair. If both colors are downsampled to the
+    /// same palette entry, the foreground color is adjusted so text remains legible.
+    pub fn adapt_pair<C>(&self, fg: Rgb, bg: Rgb) -> (Option<C>, Option<C>)
+    where
+        C: AdaptableColor + From<(u8, u8, u8)>,
+    {
+        let adapted_fg = self.adapt_color(C::from((fg.red, fg.green, fg.blue)));
+        let adapted_bg = self.adapt_color(C::from((bg.red, bg.green, bg.blue)));
+        let (Some(adapted_fg), Some(adapted_bg)) = (adapted_fg, adapted_bg) else {
+            return (None, None);
+        };
+        if let (Some(fg_16), Some(bg_16)) = (adapted_fg.as_ansi_16(), adapted_bg.as_ansi_16())
+            && fg_16 == bg_16
+        {
+            // Bright variants can't be used with 8 colors, so fall back to black or white
+            if *self == Self::Ansi8 {
+                let contrast = if is_light(bg) {
+                    AnsiColor::Black
+                } else {
+                    AnsiColor::White
+                };
+                return (Some(C::from_ansi_16(contrast)), Some(adapted_bg));
+            }
+            return (
+                Some(C::from_ansi_16(fg_16.bright(!fg_16.is_bright()))),
+                Some(adapted_bg),
+            );
+        }
+        if let (Some(fg_256), Some(bg_256)) = (adapted_fg.as_ansi_256(), adapted_bg.as_ansi_256())
+            && fg_256 == bg_256
+        {
+            let contrast = if is_light(bg) { 16 } else { 231 };
+            return (
+                Some(C::from_ansi_256(Ansi256Color(contrast))),
+                Some(adapted_bg),
+            );
+        }
+        (Some(adapted_fg), Some(adapted_bg))
+    }
+
+    /// Adapts `a` if it contrasts enough with the background to be readable, otherwise adapts
+    /// `b`. Contrast is measured with the WCAG contrast ratio after both colors are adapted, and
+    /// a ratio of at least 4.5 is considered readable.
+    ///
+    /// If neither color contrasts enough, the one with the higher contrast is returned. Returns
+    /// [`None`] if the profile doesn't support colors.
+    pub fn pick_contrasting<C>(&self, bg: Rgb, a: C, b: C) -> Option<C>
+    where
+        C: AdaptableColor,
+    {
+        // WCAG AA threshold for normal text
+        const MIN_CONTRAST: f32 = 4.5;
+        let bg = RgbColor(bg.red, bg.green, bg.blue);
+        let contrast = |color: &C| {
+            to_rgb(color)
+                .map(|rgb| contrast_ratio(rgb, bg))
+                .unwrap_or_default()
+        };
+        let a = self.adapt_color(a)?;
+        let b = self.adapt_color(b)?;
+        let contrast_a = contrast(&a);
+        if contrast_a >= MIN_CONTRAST || contrast_a >= contrast(&b) {
+            Some(a)
+        } else {
+            Some(b)
+        }
+    }
+
+    /// Adapts each color in the gradient while preserving its ordering. If the input is
+    /// monotonic in luminance, the adapted colors are kept monotonic in the same direction by
+    /// reusing the previous adapted color whenever the next one would reverse the ordering. This
+    /// prevents visual artifacts such as bands that flip back and forth in heatmaps.
+    ///
+    /// Gradients that aren't monotonic in luminance are adapted the same as
+    /// [`adapt_color`](Self::adapt_color).
+    pub fn adapt_gradient<C>(&self, colors: &[C]) -> Vec<Option<C>>
+    where
+        C: AdaptableColor + Clone,
+    {
+        let mut adapted: Vec<_> = colors
+            .iter()
+            .map(|color| self.adapt_color(color.clone()))
+            .collect();
+        let luminances: Option<Vec<f32>> = colors
+            .iter()
+            .map(|color| to_rgb(color).map(relative_luminance))
+            .collect();
+        let Some(luminances) = luminances else {
+            return adapted;
+        };
+        let ascending = luminances.windows(2).all(|w| w[0] <= w[1]);
+        let descending = luminances.windows(2).all(|w| w[0] >= w[1]);
+        if !ascending && !descending {
+            return adapted;
+        }
+        let luminance = |color: &Option<C>| color.as_ref().and_then(to_rgb).map(relative_luminance);
+        for i in 1..adapted.len() {
+            let (Some(prev), Some(current)) = (luminance(&adapted[i - 1]), luminance(&adapted[i]))
+            else {
+                continue;
+            };
+            if (ascending && current < prev) || (descending && current > prev) {
+                adapted[i] = adapted[i - 1].clone();
+            }
+        }
+        adapted
+    }
+
+    /// Converts a row of RGB pixels into color indices, such as for rendering an image as
+    /// colored blocks. Each pixel is converted to an ANSI 256 index, or an ANSI 16 index if the
+    /// profile is [`Ansi8`](Self::Ansi8) or [`Ansi16`](Self::Ansi16).
+    /// [`TrueColor`](Self::TrueColor) profiles also
+    /// return ANSI 256 indices since the output is an index.
+    ///
+    /// Conversions are cached for the duration of the call, so repeated pixels are only
+    /// converted once. Returns an empty [`Vec`] if the profile doesn't support color.
+    pub fn adapt_scanline(&self, pixels: &[RgbColor]) -> Vec<u8> {
+        if *self < Self::Ansi8 {
+            return Vec::new();
+        }
+        let mut cache = HashMap::new();
+        pixels
+            .iter()
+            .map(|pixel| {
+                *cache.entry(*pixel).or_insert_with(|| {
+                    let index = rgb_to_ansi256(*pixel);
+                    if *self <= Self::Ansi16 {
+                        self.clamp_ansi16(ansi256_to_ansi16(index)) as u8
+                    } else {
+                        index
+                    }
+                })
+            })
+            .collect()
+    }
+
     /// Adapts the style into its nearest compatible variant.
+    ///
+    /// If the profile is [`TrueColor`](Self::TrueColor), the style is returned unchanged. If the
+    /// profile is [`NoTty`](Self::NoTty), the default style is returned since no styling should
+    /// be emitted.
     pub fn adapt_style<S>(&self, mut style: S) -> S
     where
         S: AdaptableStyle,
@@ -55,6 +414,10 @@ impl TermProfile {
         if *self == Self::NoTty {
             return S::default();
         }
+        // Every color is supported, nothing to adapt
+        if *self == Self::TrueColor {
+            return style;
+        }
         if let Some(color) = style.get_fg_color() {
             style = style.fg_color(self.adapt_color(color));
         }
@@ -66,6 +429,191 @@ impl TermProfile {
         }
         style
     }
+
+    /// Adapts the style like [`adapt_style`](Self::adapt_style), but preserves some visual
+    /// hierarchy at [`NoColor`](Self::NoColor) by converting the foreground color's luminance
+    /// into a text effect before the color is removed. Dark colors are converted to
+    /// [`Effects::DIMMED`](anstyle::Effects::DIMMED) and light colors are converted to
+    /// [`Effects::BOLD`](anstyle::Effects::BOLD). ANSI 16 colors are converted with
+    /// [`ansi16_to_intensity`].
+    #[cfg(feature = "convert-anstyle")]
+    pub fn adapt_style_luminance(&self, style: Style) -> Style {
+        if *self != Self::NoColor {
+            return self.adapt_style(style);
+        }
+        let effect = style.get_fg_color().and_then(|c| match c {
+            Color::Ansi(ansi) => Some(ansi16_to_intensity(ansi)),
+            _ => to_rgb(&c).map(|rgb| {
+                if is_light(Rgb {
+                    red: rgb.r(),
+                    green: rgb.g(),
+                    blue: rgb.b(),
+                }) {
+                    Effects::BOLD
+                } else {
+                    Effects::DIMMED
+                }
+            }),
+        });
+        let style = self.adapt_style(style);
+        match effect {
+            Some(effect) => style.effects(style.get_effects() | effect),
+            None => style,
+        }
+    }
+
+    /// Adapts the style and converts it into a different style type, such as when a style is
+    /// defined with one library but rendered with another. `convert` is used to convert each
+    /// adapted color into the target color type.
+    ///
+    /// Only colors are converted since [`AdaptableStyle`] doesn't expose any other properties.
+    pub fn adapt_into<S1, S2, F>(&self, style: S1, mut convert: F) -> S2
+    where
+        S1: AdaptableStyle,
+        S2: AdaptableStyle,
+        F: FnMut(S1::Color) -> S2::Color,
+    {
+        let style = self.adapt_style(style);
+        S2::default()
+            .fg_color(style.get_fg_color().map(&mut convert))
+            .bg_color(style.get_bg_color().map(&mut convert))
+            .underline_color(style.get_underline_color().map(&mut convert))
+    }
+
+    /// Adapts the style in place. This behaves the same as [`adapt_style`](Self::adapt_style),
+    /// but can be used to update a reusable style without moving it.
+    pub fn adapt_style_in_place<S>(&self, style: &mut S)
+    where
+        S: AdaptableStyle,
+    {
+        *style = self.adapt_style(std::mem::take(style));
+    }
+
+    /// Adapts the color and renders the escape sequence for setting it as the foreground color.
+    ///
+    /// Returns an empty string if the color can't be displayed with the current profile.
+    pub fn fg_escape<C>(&self, color: C) -> String
+    where
+        C: AdaptableColor,
+    {
+        self.adapt_color(color)
+            .and_then(|c| to_anstyle_color(&c))
+            .map(|c| c.render_fg().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Adapts the color and renders the escape sequence for setting it as the background color.
+    ///
+    /// Returns an empty string if the color can't be displayed with the current profile.
+    pub fn bg_escape<C>(&self, color: C) -> String
+    where
+        C: AdaptableColor,
+    {
+        self.adapt_color(color)
+            .and_then(|c| to_anstyle_color(&c))
+            .map(|c| c.render_bg().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Adapts the color and writes the escape sequence for setting it as the foreground color.
+    ///
+    /// Nothing is written if the color can't be displayed with the current profile.
+    pub fn write_fg<W, C>(&self, writer: &mut W, color: C) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+        C: AdaptableColor,
+    {
+        if let Some(color) = self.adapt_color(color).and_then(|c| to_anstyle_color(&c)) {
+            write!(writer, "{}", color.render_fg())?;
+        }
+        Ok(())
+    }
+
+    /// Adapts the color and writes the escape sequence for setting it as the background color.
+    ///
+    /// Nothing is written if the color can't be displayed with the current profile.
+    pub fn write_bg<W, C>(&self, writer: &mut W, color: C) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+        C: AdaptableColor,
+    {
+        if let Some(color) = self.adapt_color(color).and_then(|c| to_anstyle_color(&c)) {
+            write!(writer, "{}", color.render_bg())?;
+        }
+        Ok(())
+    }
+
+    /// Renders the colors available to the profile as a grid of colored blocks. This may be
+    /// useful for debugging.
+    ///
+    /// The 8, 16, or 256 color palettes are rendered for [`Ansi8`](Self::Ansi8),
+    /// [`Ansi16`](Self::Ansi16), and [`Ansi256`](Self::Ansi256) respectively and an RGB gradient is rendered for
+    /// [`TrueColor`](Self::TrueColor). An empty string is returned if colors aren't supported.
+    pub fn swatch_string(&self) -> String {
+        let colors: Vec<Color> = match self {
+            Self::NoTty | Self::NoColor => return String::new(),
+            Self::Ansi8 => (0..8).map(|i| Ansi256Color(i).into()).collect(),
+            Self::Ansi16 => (0..16).map(|i| Ansi256Color(i).into()).collect(),
+            Self::Ansi256 => (0..=255).map(|i| Ansi256Color(i).into()).collect(),
+            Self::TrueColor => {
+                let gradient = |i: u16| (i * 255 / (GRADIENT_LEN - 1)) as u8;
+                let ramps: [fn(u8) -> RgbColor; 4] = [
+                    |v| RgbColor(v, 0, 0),
+                    |v| RgbColor(0, v, 0),
+                    |v| RgbColor(0, 0, v),
+                    |v| RgbColor(v, v, v),
+                ];
+                ramps
+                    .iter()
+                    .flat_map(|ramp| (0..GRADIENT_LEN).map(move |i| ramp(gradient(i)).into()))
+                    .collect()
+            }
+        };
+        let row_len = match self {
+            // Split normal and bright colors into separate rows
+            Self::Ansi8 | Self::Ansi16 => 8,
+            Self::TrueColor => GRADIENT_LEN as usize,
+            _ => SWATCH_ROW_LEN,
+        };
+        let reset = anstyle::Reset.render().to_string();
+        colors
+            .chunks(row_len)
+            .map(|row| {
+                let mut line: String = row
+                    .iter()
+                    .map(|color| format!("{}  ", self.bg_escape(*color)))
+                    .collect();
+                line.push_str(&reset);
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub(crate) fn to_rgb<C>(color: &C) -> Option<RgbColor>
+where
+    C: AdaptableColor,
+{
+    color
+        .as_rgb()
+        .or_else(|| color.as_ansi_256().map(ansi256_to_rgb))
+        .or_else(|| {
+            color
+                .as_ansi_16()
+                .map(|c| ansi256_to_rgb(Ansi256Color::from(c)))
+        })
+}
+
+fn to_anstyle_color<C>(color: &C) -> Option<Color>
+where
+    C: AdaptableColor,
+{
+    color
+        .as_ansi_16()
+        .map(Color::from)
+        .or_else(|| color.as_ansi_256().map(Color::from))
+        .or_else(|| color.as_rgb().map(Color::from))
 }
 
 /// Converts the indexed ANSI color into its nearest 16-color variant.
@@ -91,6 +639,28 @@ pub fn ansi256_to_ansi16(ansi256_index: u8) -> AnsiColor {
     }
 }
 
+/// Converts the ANSI 16 color into the intensity effect that best preserves its visual weight
+/// when colors can't be displayed. Bright colors are converted to
+/// [`Effects::BOLD`](anstyle::Effects::BOLD), dark colors are converted to
+/// [`Effects::DIMMED`](anstyle::Effects::DIMMED), and the remaining colors have no effect.
+pub fn ansi16_to_intensity(color: AnsiColor) -> Effects {
+    match color {
+        AnsiColor::Black | AnsiColor::Red | AnsiColor::Blue | AnsiColor::Magenta => Effects::DIMMED,
+        AnsiColor::Green
+        | AnsiColor::Yellow
+        | AnsiColor::Cyan
+        | AnsiColor::White
+        | AnsiColor::BrightBlack => Effects::new(),
+        AnsiColor::BrightRed
+        | AnsiColor::BrightGreen
+        | AnsiColor::BrightYellow
+        | AnsiColor::BrightBlue
+        | AnsiColor::BrightMagenta
+        | AnsiColor::BrightCyan
+        | AnsiColor::BrightWhite => Effects::BOLD,
+    }
+}
+
 #[cfg(feature = "color-cache")]
 static COLOR_CACHE: std::sync::LazyLock<std::sync::Mutex<lru::LruCache<RgbColor, u8>>> =
     std::sync::LazyLock::new(|| lru::LruCache::new(256.try_into().expect("invalid size")).into());
@@ -142,6 +712,66 @@ pub fn rgb_to_ansi256(color: RgbColor) -> u8 {
     rgb_to_ansi256_inner(color)
 }
 
+/// Converts the RGB color to the nearest ANSI 256 color that isn't in `excluded`. This is useful
+/// for keeping content colors from colliding with indices that are reserved for other purposes,
+/// such as UI chrome.
+///
+/// Like [`rgb_to_ansi256`], the first 16 colors are never returned since they're usually
+/// customized by the terminal's theme. If every other index is excluded, the nearest color is
+/// returned regardless.
+pub fn rgb_to_ansi256_excluding(color: RgbColor, excluded: &[u8]) -> u8 {
+    let nearest = rgb_to_ansi256(color);
+    if !excluded.contains(&nearest) {
+        return nearest;
+    }
+    (16..=255)
+        .filter(|index| !excluded.contains(index))
+        .min_by_key(|index| distance_squared(color, ansi256_to_rgb(Ansi256Color(*index))))
+        .unwrap_or(nearest)
+}
+
+/// Converts the RGB color into the nearest ANSI 256 color using gamma-correct distances. The
+/// color is converted from gamma-encoded sRGB into linear light and then into the
+/// [Oklab](https://bottosson.github.io/posts/oklab/) perceptual color space before comparing,
+/// which finds better matches for dark colors than [`rgb_to_ansi256`].
+///
+/// This is slower than [`rgb_to_ansi256`] since every color in the palette is compared. Like
+/// [`rgb_to_ansi256`], only the 6x6x6 color cube and the grayscale ramp are considered since the
+/// first 16 colors are usually customized by the terminal's theme.
+pub fn rgb_to_ansi256_linear(color: RgbColor) -> u8 {
+    let target = oklab(color);
+    let distance = |index: u8| {
+        ANSI_256_TO_OKLAB[index as usize]
+            .iter()
+            .zip(target)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+    };
+    (16..=255)
+        .map(|index| (index, distance(index)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or_else(|| rgb_to_ansi256(color), |(index, _)| index)
+}
+
+// The palette converted to Oklab ahead of time since every entry is compared on each conversion
+static ANSI_256_TO_OKLAB: std::sync::LazyLock<[[f32; 3]; 256]> =
+    std::sync::LazyLock::new(|| ANSI_256_TO_RGB.map(oklab));
+
+// See https://bottosson.github.io/posts/oklab/#converting-from-linear-srgb-to-oklab
+fn oklab(color: RgbColor) -> [f32; 3] {
+    let r = srgb_to_linear(color.r());
+    let g = srgb_to_linear(color.g());
+    let b = srgb_to_linear(color.b());
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
 fn get_color_index<const N: usize>(val: u8, breakpoints: [u8; N]) -> usize {
     breakpoints.iter().position(|p| val < *p).unwrap_or(N)
 }
@@ -166,20 +796,18 @@ const COLOR_INTERVALS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
 // https://github.com/charmbracelet/x/blob/f402b009fe75b24997fc2342a2605ecc3a268486/ansi/color.go
 // See https://invisible-island.net/xterm/xterm.faq.html#color_by_number
 fn rgb_to_ansi256_inner(color: RgbColor) -> u8 {
-    let srgb = Srgb::new(color.r(), color.g(), color.b());
-
-    let qr = red_color_index(srgb.red);
-    let qg = green_color_index(srgb.green);
-    let qb = blue_color_index(srgb.blue);
+    let qr = red_color_index(color.r());
+    let qg = green_color_index(color.g());
+    let qb = blue_color_index(color.b());
     let cr = COLOR_INTERVALS[qr];
     let cg = COLOR_INTERVALS[qg];
     let cb = COLOR_INTERVALS[qb];
     let color_index = (36 * qr + 6 * qg + qb + 16) as u8;
 
-    if cr == srgb.red && cg == srgb.green && cb == srgb.blue {
+    if cr == color.r() && cg == color.g() && cb == color.b() {
         return color_index;
     }
-    let average = ((srgb.red as u32 + srgb.green as u32 + srgb.blue as u32) / 3) as u8;
+    let average = ((color.r() as u32 + color.g() as u32 + color.b() as u32) / 3) as u8;
     let gray_index = if average > 238 {
         23
     } else {
@@ -187,11 +815,11 @@ fn rgb_to_ansi256_inner(color: RgbColor) -> u8 {
     };
     let gray_value = 8 + 10 * gray_index;
 
-    let color2 = Srgb::new(cr, cg, cb);
-    let gray2 = Srgb::new(gray_value, gray_value, gray_value);
+    let color2 = RgbColor(cr, cg, cb);
+    let gray2 = RgbColor(gray_value, gray_value, gray_value);
 
-    let color_distance = distance_squared(srgb, color2);
-    let gray_distance = distance_squared(srgb, gray2);
+    let color_distance = distance_squared(color, color2);
+    let gray_distance = distance_squared(color, gray2);
     if color_distance <= gray_distance {
         color_index
     } else {
@@ -199,22 +827,121 @@ fn rgb_to_ansi256_inner(color: RgbColor) -> u8 {
     }
 }
 
+// Uses the perceived brightness formula from https://www.w3.org/TR/AERT/#color-contrast
+pub(crate) fn is_light(color: Rgb) -> bool {
+    let brightness =
+        (299 * color.red as u32 + 587 * color.green as u32 + 114 * color.blue as u32) / 1000;
+    brightness > 127
+}
+
+// See https://www.w3.org/TR/WCAG21/#dfn-relative-luminance
+fn relative_luminance(color: RgbColor) -> f32 {
+    0.2126 * srgb_to_linear(color.r())
+        + 0.7152 * srgb_to_linear(color.g())
+        + 0.0722 * srgb_to_linear(color.b())
+}
+
+// Converts a gamma-encoded sRGB channel into linear light
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.03928 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// See https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+fn contrast_ratio(a: RgbColor, b: RgbColor) -> f32 {
+    let a = relative_luminance(a);
+    let b = relative_luminance(b);
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Converts the color into its nearest ANSI 256 color, regardless of the profile. This is useful
+/// if the color must always be serialized as an ANSI 256 index.
+///
+/// Returns [`None`] if the color has no ANSI 256 equivalent (such as a reset color).
+pub fn to_ansi256<C>(color: &C) -> Option<Ansi256Color>
+where
+    C: AdaptableColor,
+{
+    if let Some(color) = color.as_ansi_16() {
+        Some(color.into())
+    } else if let Some(color) = color.as_ansi_256() {
+        Some(color)
+    } else {
+        color.as_rgb().map(|c| rgb_to_ansi256(c).into())
+    }
+}
+
+/// Returns the squared distance between the original color and the color it's adapted to with
+/// the given profile. This can be used to measure how much fidelity is lost when the color is
+/// downsampled. Returns `0` if the color is unchanged.
+///
+/// If the profile doesn't support colors, [`u32::MAX`] is returned.
+pub fn color_delta(original: Rgb, profile: TermProfile) -> u32 {
+    let original = RgbColor(original.red, original.green, original.blue);
+    profile
+        .adapt_color(Color::from(original))
+        .and_then(|c| to_rgb(&c))
+        .map(|adapted| distance_squared(original, adapted))
+        .unwrap_or(u32::MAX)
+}
+
+/// Returns the color profiles where the color can't be displayed exactly and is changed by
+/// downsampling, according to [`color_delta`]. This can be used to pick colors that look the same
+/// across profiles.
+///
+/// Profiles that don't support colors are not included.
+pub fn color_breakpoints(color: Rgb) -> Vec<TermProfile> {
+    [
+        TermProfile::Ansi8,
+        TermProfile::Ansi16,
+        TermProfile::Ansi256,
+    ]
+    .into_iter()
+    .filter(|profile| color_delta(color, *profile) > 0)
+    .collect()
+}
+
 /// Converts the indexed ANSI color into its RGB equivalent.
 pub fn ansi256_to_rgb(ansi: Ansi256Color) -> RgbColor {
     ANSI_256_TO_RGB[ansi.0 as usize]
 }
 
+/// Returns the full 256 color palette as RGB values, indexed by the ANSI color number. Useful for
+/// building color pickers and previews.
+pub fn ansi256_palette() -> &'static [RgbColor; 256] {
+    &ANSI_256_TO_RGB
+}
+
+/// Returns the name of the nearest CSS named color, such as `"darkred"`. This is useful for
+/// describing colors in logs or diagnostics.
+///
+/// Returns [`None`] if the color has no RGB equivalent (such as a reset color).
+pub fn color_name<C>(color: &C) -> Option<&'static str>
+where
+    C: AdaptableColor,
+{
+    let rgb = to_rgb(color)?;
+    CSS_COLORS
+        .iter()
+        .min_by_key(|(_, named)| distance_squared(rgb, *named))
+        .map(|(name, _)| *name)
+}
+
 // Color distance is tricky. There's a bunch of ways to do it and which way is best
 // is a bit subjective.
 // After trying a bunch of methods, this seems to get the best results on average.
 // See https://stackoverflow.com/a/9085524
 // We save a bit of computational power by not taking the square root here, since
 // we only care about comparing relative distance, not absolute distances.
-fn distance_squared(rgb1: Srgb<u8>, rgb2: Srgb<u8>) -> u32 {
-    let r_mean = (rgb1.red as i32 + rgb2.red as i32) / 2;
-    let r = (rgb1.red as i32) - (rgb2.red as i32);
-    let g = (rgb1.green as i32) - (rgb2.green as i32);
-    let b = (rgb1.blue as i32) - (rgb2.blue as i32);
+fn distance_squared(rgb1: RgbColor, rgb2: RgbColor) -> u32 {
+    let r_mean = (rgb1.r() as i32 + rgb2.r() as i32) / 2;
+    let r = (rgb1.r() as i32) - (rgb2.r() as i32);
+    let g = (rgb1.g() as i32) - (rgb2.g() as i32);
+    let b = (rgb1.b() as i32) - (rgb2.b() as i32);
     ((((512 + r_mean) * r * r) >> 8) + 4 * g * g + (((767 - r_mean) * b * b) >> 8)) as u32
 }
 
@@ -0,0 +1,47 @@
+use anstyle::{Ansi256Color, RgbColor};
+
+use super::ansi256_to_rgb;
+use crate::Rgb;
+
+/// Default 16 color palette used by the Linux virtual console.
+///
+/// This differs from the xterm palette used for 16 color conversions by default. For example,
+/// yellow is closer to brown. Use this with
+/// [`TermProfile::adapt_color_with_palette`](crate::TermProfile::adapt_color_with_palette) when
+/// the output is a Linux console (`TERM=linux`).
+pub const LINUX_CONSOLE_PALETTE: [RgbColor; 16] = [
+    RgbColor(0x00, 0x00, 0x00),
+    RgbColor(0xaa, 0x00, 0x00),
+    RgbColor(0x00, 0xaa, 0x00),
+    RgbColor(0xaa, 0x55, 0x00),
+    RgbColor(0x00, 0x00, 0xaa),
+    RgbColor(0xaa, 0x00, 0xaa),
+    RgbColor(0x00, 0xaa, 0xaa),
+    RgbColor(0xaa, 0xaa, 0xaa),
+    RgbColor(0x55, 0x55, 0x55),
+    RgbColor(0xff, 0x55, 0x55),
+    RgbColor(0x55, 0xff, 0x55),
+    RgbColor(0xff, 0xff, 0x55),
+    RgbColor(0x55, 0x55, 0xff),
+    RgbColor(0xff, 0x55, 0xff),
+    RgbColor(0x55, 0xff, 0xff),
+    RgbColor(0xff, 0xff, 0xff),
+];
+
+/// Builds a 16 color palette from colors reported by the terminal, such as the results of an
+/// OSC 4 palette query. Indices that weren't reported use the default xterm palette. Indices
+/// greater than 15 are ignored.
+///
+/// Use this with
+/// [`TermProfile::adapt_color_with_palette`](crate::TermProfile::adapt_color_with_palette) to
+/// downsample colors based on the terminal's actual palette.
+pub fn palette_from_colors(colors: &[(u8, Rgb)]) -> [RgbColor; 16] {
+    let mut palette: [RgbColor; 16] =
+        std::array::from_fn(|i| ansi256_to_rgb(Ansi256Color(i as u8)));
+    for (index, rgb) in colors {
+        if let Some(color) = palette.get_mut(*index as usize) {
+            *color = RgbColor(rgb.red, rgb.green, rgb.blue);
+        }
+    }
+    palette
+}
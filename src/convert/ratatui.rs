@@ -1,6 +1,52 @@
+#[cfg(feature = "convert-anstyle")]
+use anstyle::Effects;
+#[cfg(feature = "convert-anstyle")]
+use ratatui_core::style::Modifier;
 use ratatui_core::style::{Color, Style};
 
 use super::{AdaptableColor, AdaptableStyle};
+#[cfg(feature = "convert-anstyle")]
+use crate::TermProfile;
+
+#[cfg(feature = "convert-anstyle")]
+impl TermProfile {
+    /// Adapts an [`anstyle::Style`] and converts it into the equivalent ratatui [`Style`],
+    /// including any text effects that ratatui supports.
+    pub fn adapt_to_ratatui(&self, style: anstyle::Style) -> Style {
+        let adapted: Style = self.adapt_into(style, from_anstyle_color);
+        if *self == Self::NoTty {
+            return adapted;
+        }
+        let effects = style.get_effects();
+        let modifiers = [
+            (Effects::BOLD, Modifier::BOLD),
+            (Effects::DIMMED, Modifier::DIM),
+            (Effects::ITALIC, Modifier::ITALIC),
+            (Effects::UNDERLINE, Modifier::UNDERLINED),
+            (Effects::DOUBLE_UNDERLINE, Modifier::UNDERLINED),
+            (Effects::CURLY_UNDERLINE, Modifier::UNDERLINED),
+            (Effects::DOTTED_UNDERLINE, Modifier::UNDERLINED),
+            (Effects::DASHED_UNDERLINE, Modifier::UNDERLINED),
+            (Effects::BLINK, Modifier::SLOW_BLINK),
+            (Effects::INVERT, Modifier::REVERSED),
+            (Effects::HIDDEN, Modifier::HIDDEN),
+            (Effects::STRIKETHROUGH, Modifier::CROSSED_OUT),
+        ]
+        .into_iter()
+        .filter(|(effect, _)| effects.contains(*effect))
+        .fold(Modifier::empty(), |acc, (_, modifier)| acc | modifier);
+        adapted.add_modifier(modifiers)
+    }
+}
+
+#[cfg(feature = "convert-anstyle")]
+fn from_anstyle_color(color: anstyle::Color) -> Color {
+    match color {
+        anstyle::Color::Ansi(color) => Color::from_ansi_16(color),
+        anstyle::Color::Ansi256(color) => Color::from_ansi_256(color),
+        anstyle::Color::Rgb(color) => Color::Rgb(color.r(), color.g(), color.b()),
+    }
+}
 
 impl AdaptableColor for Color {
     fn as_rgb(&self) -> Option<anstyle::RgbColor> {
@@ -118,7 +164,7 @@ impl AdaptableStyle for Style {
     }
 
     #[cfg(not(feature = "ratatui-underline-color"))]
-    fn underline_color(mut self, _color: Option<Self::Color>) -> Self {
+    fn underline_color(self, _color: Option<Self::Color>) -> Self {
         self
     }
 }
@@ -76,6 +76,11 @@ fn ansi256_to_ansi(#[case] in_color: Color, #[case] out_color: Color) {
     );
 }
 
+#[test]
+fn ratatui_reset_to_ansi256() {
+    assert_eq!(crate::to_ansi256(&Color::Reset), None);
+}
+
 #[test]
 fn ratatui_reset() {
     let res = TermProfile::Ansi16
@@ -145,3 +150,29 @@ fn profile_color_downsample_priority() {
         ProfileColor::new(Color::Rgb(0, 0, 0), TermProfile::Ansi16).ansi_256(Color::Indexed(8));
     assert_eq!(color.adapt(), Some(Color::DarkGray));
 }
+
+#[test]
+fn adapt_to_ratatui() {
+    let style = anstyle::Style::new()
+        .fg_color(Some(anstyle::RgbColor(255, 0, 0).into()))
+        .bg_color(Some(anstyle::AnsiColor::Blue.into()))
+        .effects(anstyle::Effects::BOLD | anstyle::Effects::CURLY_UNDERLINE);
+    assert_eq!(
+        Style::new()
+            .fg(Color::Indexed(196))
+            .bg(Color::Blue)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        TermProfile::Ansi256.adapt_to_ratatui(style)
+    );
+    assert_eq!(Style::new(), TermProfile::NoTty.adapt_to_ratatui(style));
+}
+
+#[test]
+fn adapt_into() {
+    let style = anstyle::Style::new().fg_color(Some(anstyle::RgbColor(255, 0, 0).into()));
+    let adapted: Style = TermProfile::Ansi256.adapt_into(style, |color| match color {
+        anstyle::Color::Ansi256(color) => Color::Indexed(color.0),
+        _ => Color::Reset,
+    });
+    assert_eq!(Style::new().fg(Color::Indexed(196)), adapted);
+}
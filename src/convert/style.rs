@@ -0,0 +1,106 @@
+use anstyle::{Color, Effects, Style};
+
+use crate::TermProfile;
+
+/// A style builder that adapts the style to the terminal's color support profile when it's
+/// finished.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileStyle {
+    style: Style,
+    profile: TermProfile,
+}
+
+impl ProfileStyle {
+    /// Creates a new [`ProfileStyle`].
+    pub fn new(profile: TermProfile) -> Self {
+        Self {
+            style: Style::new(),
+            profile,
+        }
+    }
+
+    /// Sets the foreground color.
+    pub fn fg<C>(mut self, color: C) -> Self
+    where
+        C: Into<Color>,
+    {
+        self.style = self.style.fg_color(Some(color.into()));
+        self
+    }
+
+    /// Sets the background color.
+    pub fn bg<C>(mut self, color: C) -> Self
+    where
+        C: Into<Color>,
+    {
+        self.style = self.style.bg_color(Some(color.into()));
+        self
+    }
+
+    /// Sets the underline color.
+    pub fn underline_color<C>(mut self, color: C) -> Self
+    where
+        C: Into<Color>,
+    {
+        self.style = self.style.underline_color(Some(color.into()));
+        self
+    }
+
+    /// Adds the text effects.
+    pub fn effects(mut self, effects: Effects) -> Self {
+        self.style = self.style.effects(self.style.get_effects() | effects);
+        self
+    }
+
+    /// Adds the bold effect.
+    pub fn bold(self) -> Self {
+        self.effects(Effects::BOLD)
+    }
+
+    /// Adds the dimmed effect.
+    pub fn dimmed(self) -> Self {
+        self.effects(Effects::DIMMED)
+    }
+
+    /// Adds the italic effect.
+    pub fn italic(self) -> Self {
+        self.effects(Effects::ITALIC)
+    }
+
+    /// Adds the underline effect.
+    pub fn underline(self) -> Self {
+        self.effects(Effects::UNDERLINE)
+    }
+
+    /// Returns the style adapted to the profile.
+    pub fn finish(self) -> Style {
+        self.profile.adapt_style(self.style)
+    }
+}
+
+impl TermProfile {
+    /// Creates a new [`ProfileStyle`] bound to this profile. The style is adapted when
+    /// [`finish`](ProfileStyle::finish) is called, so adaptation can't be forgotten.
+    ///
+    /// ```
+    /// use termprofile::TermProfile;
+    /// use termprofile::anstyle::{Ansi256Color, Effects, RgbColor, Style};
+    ///
+    /// let style = TermProfile::Ansi256
+    ///     .styled()
+    ///     .fg(RgbColor(255, 0, 0))
+    ///     .bg(RgbColor(0, 0, 0))
+    ///     .bold()
+    ///     .finish();
+    /// assert_eq!(
+    ///     Style::new()
+    ///         .fg_color(Some(Ansi256Color(196).into()))
+    ///         .bg_color(Some(Ansi256Color(16).into()))
+    ///         .effects(Effects::BOLD),
+    ///     style
+    /// );
+    /// ```
+    pub fn styled(&self) -> ProfileStyle {
+        ProfileStyle::new(*self)
+    }
+}
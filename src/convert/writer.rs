@@ -0,0 +1,250 @@
+use std::io;
+
+use anstyle::{Ansi256Color, AnsiColor, Color, RgbColor};
+
+use crate::TermProfile;
+
+const ESC: u8 = 0x1b;
+// Guard against unbounded buffering if the input contains a malformed escape sequence
+const MAX_SEQUENCE_LEN: usize = 256;
+
+/// Writer that adapts SGR color sequences to the given profile before forwarding them to the
+/// inner writer.
+///
+/// Escape sequences that are split across multiple calls to [`write`](io::Write::write) are
+/// buffered until they're complete. Escape sequences other than SGR sequences are passed through
+/// unchanged.
+#[derive(Debug)]
+pub struct AdaptingWriter<W> {
+    inner: W,
+    profile: TermProfile,
+    pending: Vec<u8>,
+}
+
+impl<W> AdaptingWriter<W>
+where
+    W: io::Write,
+{
+    /// Creates a new [`AdaptingWriter`].
+    pub fn new(inner: W, profile: TermProfile) -> Self {
+        Self {
+            inner,
+            profile,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the inner writer. Any incomplete escape sequence will be discarded.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn process(&mut self, buf: &[u8], out: &mut Vec<u8>) {
+        for &byte in buf {
+            if self.pending.is_empty() {
+                if byte == ESC {
+                    self.pending.push(byte);
+                } else {
+                    out.push(byte);
+                }
+                continue;
+            }
+
+            self.pending.push(byte);
+            if self.pending.len() == 2 {
+                if byte != b'[' {
+                    // Not a CSI sequence
+                    out.append(&mut self.pending);
+                }
+                continue;
+            }
+            if (0x40..=0x7e).contains(&byte) {
+                let params = &self.pending[2..self.pending.len() - 1];
+                // Sequences with private markers or intermediate bytes can also end in 'm'
+                // (ex: xterm's modifyOtherKeys), but they aren't SGR sequences
+                if byte == b'm'
+                    && params
+                        .iter()
+                        .all(|b| matches!(b, b'0'..=b'9' | b';' | b':'))
+                {
+                    if let Some(sgr) = adapt_sgr(self.profile, params) {
+                        out.extend_from_slice(&sgr);
+                    }
+                    self.pending.clear();
+                } else {
+                    out.append(&mut self.pending);
+                }
+            } else if self.pending.len() >= MAX_SEQUENCE_LEN {
+                out.append(&mut self.pending);
+            }
+        }
+    }
+}
+
+impl<W> io::Write for AdaptingWriter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(buf.len());
+        self.process(buf, &mut out);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ColorTarget {
+    Fg,
+    Bg,
+    Underline,
+}
+
+fn adapt_sgr(profile: TermProfile, params: &[u8]) -> Option<Vec<u8>> {
+    if profile == TermProfile::NoTty {
+        return None;
+    }
+    let Ok(params) = std::str::from_utf8(params) else {
+        return Some(raw_sgr(params));
+    };
+    // An empty parameter list is equivalent to a reset
+    if params.is_empty() {
+        return Some(raw_sgr(params.as_bytes()));
+    }
+    let Some(codes) = params
+        .split(';')
+        .map(|p| {
+            if p.is_empty() {
+                Some(0)
+            } else {
+                p.parse::<u16>().ok()
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        // Colon-separated subparameters and other unusual formats are passed through as-is
+        return Some(raw_sgr(params.as_bytes()));
+    };
+
+    let mut adapted = Vec::new();
+    let mut codes = codes.into_iter();
+    while let Some(code) = codes.next() {
+        let (target, color) = match code {
+            30..=37 => (ColorTarget::Fg, ansi_color(code - 30, false)),
+            90..=97 => (ColorTarget::Fg, ansi_color(code - 90, true)),
+            40..=47 => (ColorTarget::Bg, ansi_color(code - 40, false)),
+            100..=107 => (ColorTarget::Bg, ansi_color(code - 100, true)),
+            38 | 48 | 58 => {
+                let target = match code {
+                    38 => ColorTarget::Fg,
+                    48 => ColorTarget::Bg,
+                    _ => ColorTarget::Underline,
+                };
+                match parse_extended_color(&mut codes) {
+                    Some(color) => (target, color),
+                    None => return Some(raw_sgr(params.as_bytes())),
+                }
+            }
+            _ => {
+                adapted.push(code.to_string());
+                continue;
+            }
+        };
+        if let Some(color) = profile.adapt_color(color) {
+            adapted.push(color_params(target, color));
+        }
+    }
+    if adapted.is_empty() {
+        return None;
+    }
+    Some(raw_sgr(adapted.join(";").as_bytes()))
+}
+
+fn raw_sgr(params: &[u8]) -> Vec<u8> {
+    let mut sgr = vec![ESC, b'['];
+    sgr.extend_from_slice(params);
+    sgr.push(b'm');
+    sgr
+}
+
+fn parse_extended_color<I>(codes: &mut I) -> Option<Color>
+where
+    I: Iterator<Item = u16>,
+{
+    match codes.next()? {
+        5 => Some(Ansi256Color(u8::try_from(codes.next()?).ok()?).into()),
+        2 => {
+            let r = u8::try_from(codes.next()?).ok()?;
+            let g = u8::try_from(codes.next()?).ok()?;
+            let b = u8::try_from(codes.next()?).ok()?;
+            Some(RgbColor(r, g, b).into())
+        }
+        _ => None,
+    }
+}
+
+fn ansi_color(index: u16, bright: bool) -> Color {
+    let color = match index {
+        0 => AnsiColor::Black,
+        1 => AnsiColor::Red,
+        2 => AnsiColor::Green,
+        3 => AnsiColor::Yellow,
+        4 => AnsiColor::Blue,
+        5 => AnsiColor::Magenta,
+        6 => AnsiColor::Cyan,
+        _ => AnsiColor::White,
+    };
+    color.bright(bright).into()
+}
+
+fn color_params(target: ColorTarget, color: Color) -> String {
+    match (target, color) {
+        (ColorTarget::Fg, Color::Ansi(ansi)) => ansi_code(ansi, 30).to_string(),
+        (ColorTarget::Bg, Color::Ansi(ansi)) => ansi_code(ansi, 40).to_string(),
+        // There's no dedicated code for 16 color underlines
+        (ColorTarget::Underline, Color::Ansi(ansi)) => format!("58;5;{}", ansi as u8),
+        (target, Color::Ansi256(color)) => format!("{};5;{}", extended_code(target), color.0),
+        (target, Color::Rgb(color)) => format!(
+            "{};2;{};{};{}",
+            extended_code(target),
+            color.r(),
+            color.g(),
+            color.b()
+        ),
+    }
+}
+
+fn ansi_code(color: AnsiColor, base: u16) -> u16 {
+    let index = color.bright(false) as u16;
+    if color.is_bright() {
+        base + 60 + index
+    } else {
+        base + index
+    }
+}
+
+fn extended_code(target: ColorTarget) -> u16 {
+    match target {
+        ColorTarget::Fg => 38,
+        ColorTarget::Bg => 48,
+        ColorTarget::Underline => 58,
+    }
+}
+
+#[cfg(test)]
+#[path = "./writer_test.rs"]
+mod writer_test;
@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use rstest::rstest;
+
+use super::AdaptingWriter;
+use crate::TermProfile;
+
+fn write_bytewise(profile: TermProfile, input: &str) -> String {
+    let mut writer = AdaptingWriter::new(Vec::new(), profile);
+    for byte in input.as_bytes() {
+        writer.write_all(&[*byte]).unwrap();
+    }
+    writer.flush().unwrap();
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, "\x1b[38;2;255;0;0mhi\x1b[0m")]
+#[case(TermProfile::Ansi256, "\x1b[38;5;196mhi\x1b[0m")]
+#[case(TermProfile::Ansi16, "\x1b[91mhi\x1b[0m")]
+#[case(TermProfile::NoColor, "hi\x1b[0m")]
+#[case(TermProfile::NoTty, "hi")]
+fn truecolor_bytewise(#[case] profile: TermProfile, #[case] expected: &str) {
+    let res = write_bytewise(profile, "\x1b[38;2;255;0;0mhi\x1b[0m");
+    assert_eq!(res, expected);
+}
+
+#[rstest]
+#[case(TermProfile::Ansi256, "\x1b[1;48;5;16;4mhi")]
+#[case(TermProfile::Ansi16, "\x1b[1;40;4mhi")]
+#[case(TermProfile::NoColor, "\x1b[1;4mhi")]
+fn mixed_params(#[case] profile: TermProfile, #[case] expected: &str) {
+    let res = write_bytewise(profile, "\x1b[1;48;2;0;0;0;4mhi");
+    assert_eq!(res, expected);
+}
+
+#[test]
+fn ansi256_to_ansi16() {
+    let res = write_bytewise(TermProfile::Ansi16, "\x1b[38;5;167mhi");
+    assert_eq!(res, "\x1b[33mhi");
+}
+
+#[test]
+fn non_sgr_passthrough() {
+    let input = "\x1b[2J\x1b]0;title\x07text";
+    let res = write_bytewise(TermProfile::NoColor, input);
+    assert_eq!(res, input);
+}
+
+#[rstest]
+#[case(TermProfile::NoTty)]
+#[case(TermProfile::NoColor)]
+#[case(TermProfile::Ansi16)]
+fn private_sequence_passthrough(#[case] profile: TermProfile) {
+    let input = "\x1b[>4;2m\x1b[?1049htext\x1b[>4m";
+    let res = write_bytewise(profile, input);
+    assert_eq!(res, input);
+}
+
+#[test]
+fn no_tty_strips_sgr_only() {
+    let res = write_bytewise(TermProfile::NoTty, "\x1b[2J\x1b[1;31m\x1b[>4;2mhi\x1b[m");
+    assert_eq!(res, "\x1b[2J\x1b[>4;2mhi");
+}
+
+#[test]
+fn split_writes() {
+    let mut writer = AdaptingWriter::new(Vec::new(), TermProfile::Ansi256);
+    writer.write_all(b"a\x1b[38;2;25").unwrap();
+    assert_eq!(writer.get_ref(), b"a");
+    writer.write_all(b"5;255;255mb").unwrap();
+    assert_eq!(writer.into_inner(), b"a\x1b[38;5;231mb");
+}
@@ -1,7 +1,8 @@
 use std::collections::{BTreeMap, HashMap};
-use std::env;
 use std::io::{self, Read};
 use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use std::{env, fmt};
 
 use crate::TermProfile;
 
@@ -11,6 +12,12 @@ use crate::TermProfile;
 
 /// Custom trait for determining if something is a terminal. This mirrors the trait from
 /// [`std::io`], but that one is sealed and not able to be implemented on custom types.
+///
+/// Every type that implements [`std::io::IsTerminal`] also implements this trait. To check a
+/// descriptor other than the standard streams, such as a pty opened by the application, pass a
+/// borrowed descriptor from [`AsFd::as_fd`](std::os::fd::AsFd::as_fd) (or
+/// [`AsHandle::as_handle`](https://doc.rust-lang.org/std/os/windows/io/trait.AsHandle.html) on
+/// Windows).
 pub trait IsTerminal {
     /// Returns true if the current object is a terminal.
     fn is_terminal(&self) -> bool;
@@ -25,6 +32,21 @@ where
     }
 }
 
+// Uses the value from DetectorSettings::force_is_terminal if it's set
+pub(crate) struct ForcedTerminal<'a, T> {
+    pub(crate) out: &'a T,
+    pub(crate) is_terminal: Option<bool>,
+}
+
+impl<T> IsTerminal for ForcedTerminal<'_, T>
+where
+    T: IsTerminal,
+{
+    fn is_terminal(&self) -> bool {
+        self.is_terminal.unwrap_or_else(|| self.out.is_terminal())
+    }
+}
+
 /// Trait for implementing custom environment variable sources. This is useful if you want to
 /// source environment variables from somewhere other than [`std::env::var`].
 pub trait EnvVarSource {
@@ -82,6 +104,20 @@ pub struct TermVars {
     pub windows: WindowsVars,
     /// Information sourced from terminfo.
     pub terminfo: TerminfoVars,
+    pub(crate) options: DetectorOptions,
+}
+
+// Settings that change how the profile is determined from the variables, as opposed to how the
+// variables are loaded. These are carried along with the variables so detect_with_vars can use
+// them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct DetectorOptions {
+    pub(crate) dumb_as_no_color: bool,
+    pub(crate) optimistic: bool,
+    pub(crate) multiplexer_cap: Option<TermProfile>,
+    pub(crate) strict_colorterm: bool,
+    pub(crate) screen_truecolor: bool,
+    pub(crate) ignore_terminfo_truecolor: bool,
 }
 
 /// Variables for overriding terminal behavior.
@@ -98,6 +134,9 @@ pub struct OverrideVars {
     pub no_color: TermVar,
     /// `TTY_FORCE` environment variable - forces the output to behave like a TTY.
     pub tty_force: TermVar,
+    /// `COLOR_PROFILE` environment variable - sets the profile directly, bypassing other
+    /// detection methods.
+    pub color_profile: TermVar,
 }
 
 /// Metadata about the terminal itself.
@@ -114,8 +153,30 @@ pub struct TermMetaVars {
     pub term_program: TermVar,
     /// `TERM_PROGRAM_VERSION` environment variable - current terminal program version.
     pub term_program_version: TermVar,
+    /// `LC_TERMINAL` environment variable - set by iTerm2 and forwarded over SSH by default.
+    pub lc_terminal: TermVar,
+    /// `LC_TERMINAL_VERSION` environment variable - set by iTerm2 and forwarded over SSH by
+    /// default.
+    pub lc_terminal_version: TermVar,
     /// Whether the DCS query for true color support returned true.
     pub dcs_response: bool,
+    /// Whether the terminal reported support for the Kitty graphics protocol during the DCS
+    /// query. This is only checked if [`QueryTerminal::query_kitty_graphics`] is enabled.
+    pub kitty_graphics: bool,
+    /// Background color of the terminal, if known.
+    pub background: Option<Rgb>,
+    /// `ZELLIJ` environment variable - set if running in zellij.
+    pub zellij: TermVar,
+    /// `PUTTY` environment variable - set by some PuTTY configurations.
+    pub putty: TermVar,
+    /// `VSCODE_INJECTION` environment variable - set by VS Code's shell integration.
+    pub vscode_injection: TermVar,
+    /// `SSH_CONNECTION` environment variable - set by the SSH server.
+    pub ssh_connection: TermVar,
+    /// `SSH_CLIENT` environment variable - set by the SSH server.
+    pub ssh_client: TermVar,
+    /// `SSH_TTY` environment variable - set by the SSH server if a TTY was allocated.
+    pub ssh_tty: TermVar,
 }
 
 /// Windows information.
@@ -192,17 +253,80 @@ pub struct TerminfoVars {
     pub max_colors: Option<i32>,
     /// Truecolor terminfo extension, this is non-standard.
     pub truecolor: Option<bool>,
+    /// Whether a terminfo entry was found for the current `TERM` value. An entry may be found
+    /// without reporting any colors, which indicates a monochrome terminal.
+    pub found: bool,
 }
 
 pub(crate) const TERM: &str = "TERM";
 pub(crate) const TERM_PROGRAM: &str = "TERM_PROGRAM";
 pub(crate) const TERM_PROGRAM_VERSION: &str = "TERM_PROGRAM_VERSION";
 pub(crate) const COLORTERM: &str = "COLORTERM";
+pub(crate) const LC_TERMINAL: &str = "LC_TERMINAL";
+pub(crate) const LC_TERMINAL_VERSION: &str = "LC_TERMINAL_VERSION";
 pub(crate) const CLICOLOR_FORCE: &str = "CLICOLOR_FORCE";
 pub(crate) const CLICOLOR: &str = "CLICOLOR";
 pub(crate) const FORCE_COLOR: &str = "FORCE_COLOR";
 pub(crate) const NO_COLOR: &str = "NO_COLOR";
 pub(crate) const TTY_FORCE: &str = "TTY_FORCE";
+pub(crate) const ZELLIJ: &str = "ZELLIJ";
+pub(crate) const COLOR_PROFILE: &str = "COLOR_PROFILE";
+pub(crate) const PUTTY: &str = "PUTTY";
+pub(crate) const VSCODE_INJECTION: &str = "VSCODE_INJECTION";
+pub(crate) const SSH_CONNECTION: &str = "SSH_CONNECTION";
+pub(crate) const SSH_CLIENT: &str = "SSH_CLIENT";
+pub(crate) const SSH_TTY: &str = "SSH_TTY";
+pub(crate) const MOSH_KEY: &str = "MOSH_KEY";
+pub(crate) const MOSH_SERVER_NETWORK_TMOUT: &str = "MOSH_SERVER_NETWORK_TMOUT";
+
+/// Every environment variable that's read during detection. This can be used to capture the
+/// parts of the environment that affect the detected profile.
+pub const RELEVANT_ENV_VARS: &[&str] = &[
+    // Overrides
+    NO_COLOR,
+    FORCE_COLOR,
+    CLICOLOR,
+    CLICOLOR_FORCE,
+    TTY_FORCE,
+    COLOR_PROFILE,
+    // Terminal metadata
+    TERM,
+    COLORTERM,
+    TERM_PROGRAM,
+    TERM_PROGRAM_VERSION,
+    LC_TERMINAL,
+    LC_TERMINAL_VERSION,
+    ZELLIJ,
+    PUTTY,
+    VSCODE_INJECTION,
+    SSH_CONNECTION,
+    SSH_CLIENT,
+    SSH_TTY,
+    MOSH_KEY,
+    MOSH_SERVER_NETWORK_TMOUT,
+    // Multiplexers
+    "TMUX",
+    // Special cases
+    "GITHUB_ACTIONS",
+    "GITEA_ACTIONS",
+    "CIRCLECI",
+    "GITLAB_CI",
+    "DRONE",
+    "CI_NAME",
+    "GOOGLE_CLOUD_SHELL",
+    "APPVEYOR",
+    "TRAVIS",
+    "BUILDKITE",
+    "AGENT_NAME",
+    "TEAMCITY_VERSION",
+    "TF_BUILD",
+    "CIRRUS_CI",
+    "ConEmuANSI",
+    "CI",
+    // Windows
+    "ANSICON",
+    "ANSICON_VER",
+];
 
 pub(crate) const SCREEN: &str = "screen";
 pub(crate) const TMUX: &str = "tmux";
@@ -210,6 +334,37 @@ pub(crate) const DUMB: &str = "dumb";
 pub(crate) const TC: &str = "Tc";
 pub(crate) const RGB: &str = "RGB";
 
+const MINTTY: &str = "mintty";
+const ITERM: &str = "iterm.app";
+const APPLE_TERMINAL: &str = "apple_terminal";
+const VSCODE: &str = "vscode";
+const KNOWN_TERM_PROGRAMS: [&str; 4] = [MINTTY, ITERM, APPLE_TERMINAL, VSCODE];
+
+const PUTTY_TERM: &str = "putty";
+const DIRECT: &str = "direct";
+const TRUECOLOR_TERMS: [&str; 10] = [
+    "alacritty",
+    "contour",
+    "rio",
+    "wezterm",
+    "ghostty",
+    "kitty",
+    "foot",
+    "st",
+    "mlterm",
+    DIRECT,
+];
+// Terminals that only support the 8 base colors
+const ANSI8_TERMS: [&str; 3] = ["cons25", "pcansi", "sun-color"];
+// DOS-style terminals that emulate ANSI.SYS. Bold text uses the bright colors, so all 16 are
+// available.
+const ANSI_SYS_TERMS: [&str; 2] = ["ansi", "ansi.sys"];
+// Terminal families that support bright colors even though their terminfo entries commonly
+// report 8 colors (ex: xterm-new, rxvt, cygwin)
+const BRIGHT_TERM_PREFIXES: [&str; 7] = [
+    "xterm", "rxvt", "konsole", "cygwin", "gnome", "vte", "eterm",
+];
+
 #[cfg(feature = "terminfo")]
 fn get_ext_bool(info: &termini::TermInfo, name: &str) -> Option<bool> {
     info.extended_cap(name).map(|c| c == termini::Value::True)
@@ -222,32 +377,52 @@ impl TerminfoVars {
         S: EnvVarSource,
         Q: QueryTerminal,
     {
+        if let Some(vars) = Self::from_override(settings) {
+            return vars;
+        }
         let term = source.var(TERM).unwrap_or_default();
         if settings.enable_terminfo
             && let Ok(info) = termini::TermInfo::from_name(&term)
         {
-            Self {
-                // Tc/RGB are newer terminfo extensions that seem to be sparsely documented, but
-                // some newer terminals support it since the max colors property has
-                // some compatibility issues
-                truecolor: get_ext_bool(&info, TC).or_else(|| get_ext_bool(&info, RGB)),
-                max_colors: info.number_cap(termini::NumberCapability::MaxColors),
-            }
+            Self::from_terminfo(&info)
         } else {
             Self {
                 truecolor: None,
                 max_colors: None,
+                found: false,
             }
         }
     }
 
+    #[cfg(feature = "terminfo")]
+    fn from_terminfo(info: &termini::TermInfo) -> Self {
+        Self {
+            // Tc/RGB are newer terminfo extensions that seem to be sparsely documented, but
+            // some newer terminals support it since the max colors property has
+            // some compatibility issues
+            truecolor: get_ext_bool(info, TC).or_else(|| get_ext_bool(info, RGB)),
+            max_colors: info.number_cap(termini::NumberCapability::MaxColors),
+            found: true,
+        }
+    }
+
     #[cfg(not(feature = "terminfo"))]
-    fn from_env<S, Q>(_source: &S, _settings: &DetectorSettings<Q>) -> Self
+    fn from_env<S, Q>(_source: &S, settings: &DetectorSettings<Q>) -> Self
     where
         S: EnvVarSource,
         Q: QueryTerminal,
     {
-        Self::default()
+        Self::from_override(settings).unwrap_or_default()
+    }
+
+    fn from_override<Q>(settings: &DetectorSettings<Q>) -> Option<Self> {
+        settings
+            .terminfo_override
+            .map(|(max_colors, truecolor)| Self {
+                max_colors,
+                truecolor,
+                found: true,
+            })
     }
 }
 
@@ -268,56 +443,192 @@ impl TermVars {
         T: IsTerminal,
         Q: QueryTerminal,
     {
+        let start = Instant::now();
+        let budget_exceeded = |settings: &DetectorSettings<Q>| {
+            settings
+                .total_budget
+                .is_some_and(|budget| start.elapsed() >= budget)
+        };
+
+        let meta = TermMetaVars::from_source(source, out, &mut settings);
+        if budget_exceeded(&settings) {
+            settings.enable_tmux_info = false;
+        }
+        let tmux = TmuxVars::from_source(source, &settings);
+        if budget_exceeded(&settings) {
+            settings.enable_terminfo = false;
+        }
         Self {
-            meta: TermMetaVars::from_source(source, out, &mut settings),
+            meta,
             overrides: OverrideVars::from_source(source),
             special: SpecialVars::from_source(source),
-            tmux: TmuxVars::from_source(source, &settings),
+            tmux,
             terminfo: TerminfoVars::from_env(source, &settings),
             windows: WindowsVars::from_source(source),
+            options: settings.options,
+        }
+    }
+
+    // Loads the variables that require querying the terminal or running external programs.
+    // Used to fill in variables that were loaded with these disabled.
+    #[cfg(feature = "color-cache")]
+    fn load_probes<S, Q, T>(&mut self, source: &S, out: &T, settings: DetectorSettings<Q>)
+    where
+        S: EnvVarSource,
+        T: IsTerminal,
+        Q: QueryTerminal,
+    {
+        self.tmux = TmuxVars::from_source(source, &settings);
+        self.terminfo = TerminfoVars::from_env(source, &settings);
+        #[cfg(feature = "query-detect")]
+        if settings.enable_query {
+            // Same as a regular load, a failed query is treated as no response
+            let _ = self.refresh_dcs(source, out, settings);
         }
+        #[cfg(not(feature = "query-detect"))]
+        let _ = out;
+    }
+}
+
+/// Terminal multiplexer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Multiplexer {
+    /// tmux
+    Tmux,
+    /// GNU Screen
+    Screen,
+    /// Zellij
+    Zellij,
+}
+
+impl TermVars {
+    /// Returns the terminal multiplexer that's currently in use, if any.
+    pub fn multiplexer(&self) -> Option<Multiplexer> {
+        if self.is_tmux() {
+            Some(Multiplexer::Tmux)
+        } else if prefix_or_equal(&self.meta.term.value(), SCREEN) {
+            Some(Multiplexer::Screen)
+        } else if !self.meta.zellij.is_empty() {
+            Some(Multiplexer::Zellij)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the name of the terminal, if it's one of the terminals with special handling
+    /// during detection. The name is sourced from `TERM_PROGRAM` or `TERM` and is normalized to
+    /// lowercase (ex: `kitty`, `wezterm`, `iterm.app`).
+    pub fn terminal_name(&self) -> Option<&'static str> {
+        let (term_program, _) = self.meta.term_program();
+        if let Some(name) = KNOWN_TERM_PROGRAMS.iter().find(|p| **p == term_program) {
+            return Some(name);
+        }
+        let term = self.meta.term.value();
+        if prefix_or_equal(&term, PUTTY_TERM) {
+            return Some(PUTTY_TERM);
+        }
+        let term = term.replacen("screen.", "", 1);
+        term.split("-").find_map(|t| {
+            TRUECOLOR_TERMS
+                .iter()
+                .find(|name| **name == t && **name != DIRECT)
+                .copied()
+        })
+    }
+
+    /// Returns whether tmux reports true color support based on the `Tc` or `RGB` capabilities
+    /// in [`TmuxVars::tmux_info`]. Returns [`None`] if tmux isn't in use.
+    pub fn tmux_truecolor(&self) -> Option<bool> {
+        if !self.is_tmux() {
+            return None;
+        }
+        Some(
+            self.tmux
+                .tmux_info
+                .split('\n')
+                .any(|line| (line.contains(TC) || line.contains(RGB)) && line.contains("true")),
+        )
+    }
+
+    pub(crate) fn is_tmux(&self) -> bool {
+        !self.tmux.tmux.is_empty()
+            || prefix_or_equal(&self.meta.term.value(), TMUX)
+            || prefix_or_equal(&self.meta.term_program.value(), TMUX)
     }
 }
 
 impl TermMetaVars {
     /// Load the variables from the given source.
-    pub fn from_source<S, Q, T>(
-        source: &S,
-        out: &T,
-        #[cfg_attr(not(feature = "query-detect"), expect(unused))] settings: &mut DetectorSettings<
-            Q,
-        >,
-    ) -> Self
+    pub fn from_source<S, Q, T>(source: &S, out: &T, settings: &mut DetectorSettings<Q>) -> Self
     where
         S: EnvVarSource,
         T: IsTerminal,
         Q: QueryTerminal,
     {
+        let out = ForcedTerminal {
+            out,
+            is_terminal: settings.force_is_terminal,
+        };
         let term = TermVar::from_source(source, TERM);
         #[cfg(feature = "query-detect")]
-        let dcs_response = if settings.enable_query {
+        let query_response = if settings.enable_query {
             crate::query_detect(
                 source,
-                out,
+                &out,
                 &mut settings.query_terminal,
                 term.0.as_deref().unwrap_or_default(),
+                settings.total_budget,
+                settings.probe_color,
             )
-            .unwrap_or(false)
+            .unwrap_or_default()
         } else {
-            false
+            crate::QueryResponse::default()
         };
+        #[cfg(feature = "query-detect")]
+        let (dcs_response, kitty_graphics) =
+            (query_response.true_color, query_response.kitty_graphics);
         #[cfg(not(feature = "query-detect"))]
-        let dcs_response = false;
+        let (dcs_response, kitty_graphics) = (false, false);
         Self {
             is_terminal: out.is_terminal(),
             term,
             colorterm: TermVar::from_source(source, COLORTERM),
             term_program: TermVar::from_source(source, TERM_PROGRAM),
             term_program_version: TermVar::from_source(source, TERM_PROGRAM_VERSION),
+            lc_terminal: TermVar::from_source(source, LC_TERMINAL),
+            lc_terminal_version: TermVar::from_source(source, LC_TERMINAL_VERSION),
             dcs_response,
+            kitty_graphics,
+            background: settings.background,
+            zellij: TermVar::from_source(source, ZELLIJ),
+            putty: TermVar::from_source(source, PUTTY),
+            vscode_injection: TermVar::from_source(source, VSCODE_INJECTION),
+            ssh_connection: TermVar::from_source(source, SSH_CONNECTION),
+            ssh_client: TermVar::from_source(source, SSH_CLIENT),
+            ssh_tty: TermVar::from_source(source, SSH_TTY),
+        }
+    }
+
+    // TERM_PROGRAM isn't forwarded over SSH by default, but LC_TERMINAL is. VS Code's shell
+    // integration variable also survives if TERM_PROGRAM is unset by a shell's rc files.
+    pub(crate) fn term_program(&self) -> (String, String) {
+        if self.term_program.is_empty() && self.lc_terminal.value() == "iterm2" {
+            (ITERM.to_string(), self.lc_terminal_version.value())
+        } else if self.term_program.is_empty() && self.vscode_injection.is_truthy() {
+            (VSCODE.to_string(), String::new())
+        } else {
+            (self.term_program.value(), self.term_program_version.value())
         }
     }
 
+    /// Returns true if the current session is running over SSH. Terminal-specific variables
+    /// such as `COLORTERM` may not be forwarded over SSH, so detection may be less accurate in
+    /// this case.
+    pub fn is_ssh(&self) -> bool {
+        !self.ssh_connection.is_empty() || !self.ssh_client.is_empty() || !self.ssh_tty.is_empty()
+    }
+
     fn is_dumb(&self) -> bool {
         self.term.0.as_deref() == Some(DUMB)
     }
@@ -340,6 +651,50 @@ pub struct Rgb {
     pub blue: u8,
 }
 
+impl From<(u8, u8, u8)> for Rgb {
+    fn from((red, green, blue): (u8, u8, u8)) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+impl Rgb {
+    /// Parses a hex color string in the form `#rrggbb` or `#rgb`. The leading `#` is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseRgbError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseRgbError);
+        }
+        let component = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16)
+                .map_err(|_| ParseRgbError)?;
+            // Expand shorthand digits, ex: "f" -> "ff"
+            Ok(if len == 1 { value * 0x11 } else { value })
+        };
+        let len = match digits.len() {
+            3 => 1,
+            6 => 2,
+            _ => return Err(ParseRgbError),
+        };
+        Ok(Self {
+            red: component(0, len)?,
+            green: component(1, len)?,
+            blue: component(2, len)?,
+        })
+    }
+}
+
+/// Error returned when parsing an invalid hex color with [`Rgb::from_hex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseRgbError;
+
+impl fmt::Display for ParseRgbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid hex color")
+    }
+}
+
+impl std::error::Error for ParseRgbError {}
+
 /// Event returned by a DCS query.
 #[derive(Clone, Debug)]
 pub enum DcsEvent {
@@ -347,6 +702,23 @@ pub enum DcsEvent {
     BackgroundColor(Rgb),
     /// Device attributes returned by the terminal - used to signal the end of the query.
     DeviceAttributes,
+    /// Palette color returned by an OSC 4 query.
+    PaletteColor {
+        /// Palette index.
+        index: u8,
+        /// Color of the palette entry.
+        rgb: Rgb,
+    },
+    /// Secondary device attributes (DA2) returned by the terminal.
+    SecondaryAttributes {
+        /// Terminal type identifier (ex: `41` for xterm).
+        kind: u16,
+        /// Firmware version or patch number of the terminal.
+        version: u32,
+    },
+    /// Response to a Kitty graphics protocol query. Contains `true` if the terminal reported
+    /// that the query succeeded.
+    KittyGraphics(bool),
     /// A miscellaneous event.
     Other,
     /// Timed reading the next event.
@@ -354,6 +726,16 @@ pub enum DcsEvent {
 }
 
 /// Trait for defining a terminal source that can be queried.
+///
+/// This trait is object safe, so a terminal chosen at runtime can be used as a
+/// `Box<dyn QueryTerminal>`.
+///
+/// Applications that already own the terminal, such as TUIs with their own event loop, can
+/// implement this trait on top of their existing event source instead of creating a second
+/// terminal handle. In that case, [`setup`](QueryTerminal::setup) and
+/// [`cleanup`](QueryTerminal::cleanup) can be no-ops if the terminal is already in raw mode, and
+/// [`read_event`](QueryTerminal::read_event) should translate the application's parsed escape
+/// sequences into [`DcsEvent`]s.
 pub trait QueryTerminal: io::Write {
     /// Set up the terminal by enabling raw mode.
     fn setup(&mut self) -> io::Result<()>;
@@ -361,6 +743,152 @@ pub trait QueryTerminal: io::Write {
     fn cleanup(&mut self) -> io::Result<()>;
     /// Read the next event from the terminal.
     fn read_event(&mut self) -> io::Result<DcsEvent>;
+    /// Whether to also request the secondary device attributes (DA2). Only enable this if
+    /// [`read_event`](QueryTerminal::read_event) is able to parse the response into
+    /// [`DcsEvent::SecondaryAttributes`], otherwise the unparsed response may interfere with the
+    /// rest of the query. The default value is `false`.
+    fn query_secondary_attributes(&self) -> bool {
+        false
+    }
+    /// Whether to also query for Kitty graphics protocol support. Only enable this if
+    /// [`read_event`](QueryTerminal::read_event) is able to parse the response into
+    /// [`DcsEvent::KittyGraphics`]. The default value is `false`.
+    fn query_kitty_graphics(&self) -> bool {
+        false
+    }
+}
+
+impl<T> QueryTerminal for Box<T>
+where
+    T: QueryTerminal + ?Sized,
+{
+    fn setup(&mut self) -> io::Result<()> {
+        (**self).setup()
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        (**self).cleanup()
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        (**self).read_event()
+    }
+
+    fn query_secondary_attributes(&self) -> bool {
+        (**self).query_secondary_attributes()
+    }
+
+    fn query_kitty_graphics(&self) -> bool {
+        (**self).query_kitty_graphics()
+    }
+}
+
+impl<T> QueryTerminal for &mut T
+where
+    T: QueryTerminal + ?Sized,
+{
+    fn setup(&mut self) -> io::Result<()> {
+        (**self).setup()
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        (**self).cleanup()
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        (**self).read_event()
+    }
+
+    fn query_secondary_attributes(&self) -> bool {
+        (**self).query_secondary_attributes()
+    }
+
+    fn query_kitty_graphics(&self) -> bool {
+        (**self).query_kitty_graphics()
+    }
+}
+
+/// [`QueryTerminal`] that wraps another terminal and records the bytes written to it along with
+/// each event read from it. This is useful for debugging why a query fails on a specific terminal.
+///
+/// A mutable reference can be used as the query terminal so the recording can be inspected after
+/// detection.
+#[derive(Clone, Debug)]
+pub struct RecordingTerminal<T> {
+    inner: T,
+    written: Vec<u8>,
+    events: Vec<DcsEvent>,
+}
+
+impl<T> RecordingTerminal<T>
+where
+    T: QueryTerminal,
+{
+    /// Creates a new [`RecordingTerminal`] that wraps the given terminal.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            written: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the bytes written to the terminal.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns the events read from the terminal, in the order they were received.
+    pub fn events(&self) -> &[DcsEvent] {
+        &self.events
+    }
+
+    /// Returns the wrapped terminal.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> io::Write for RecordingTerminal<T>
+where
+    T: QueryTerminal,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T> QueryTerminal for RecordingTerminal<T>
+where
+    T: QueryTerminal,
+{
+    fn setup(&mut self) -> io::Result<()> {
+        self.inner.setup()
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.inner.cleanup()
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        let event = self.inner.read_event()?;
+        self.events.push(event.clone());
+        Ok(event)
+    }
+
+    fn query_secondary_attributes(&self) -> bool {
+        self.inner.query_secondary_attributes()
+    }
+
+    fn query_kitty_graphics(&self) -> bool {
+        self.inner.query_kitty_graphics()
+    }
 }
 
 /// Default implementation for [`QueryTerminal`] that doesn't query anything.
@@ -409,6 +937,91 @@ impl OverrideVars {
             clicolor: TermVar::from_source(source, CLICOLOR),
             clicolor_force: TermVar::from_source(source, CLICOLOR_FORCE),
             tty_force: TermVar::from_source(source, TTY_FORCE),
+            color_profile: TermVar::from_source(source, COLOR_PROFILE),
+        }
+    }
+
+    /// Returns true if `NO_COLOR` (or `CLICOLOR=never`) is set along with a variable that enables
+    /// color support, such as `FORCE_COLOR`, `COLOR_PROFILE`, or `CLICOLOR=always`.
+    /// `NO_COLOR` takes precedence in this case, which may be surprising to users, so this can be
+    /// used to emit a warning.
+    pub fn conflicting_overrides(&self) -> bool {
+        self.colors_disabled()
+            && (self
+                .color_profile_level()
+                .is_some_and(|p| p > TermProfile::NoColor)
+                || self
+                    .forced_level()
+                    .is_some_and(|p| p > TermProfile::NoColor)
+                || self.color_forced())
+    }
+
+    fn color_profile_level(&self) -> Option<TermProfile> {
+        match self.color_profile.value().as_str() {
+            "notty" => Some(TermProfile::NoTty),
+            "nocolor" => Some(TermProfile::NoColor),
+            "ansi8" => Some(TermProfile::Ansi8),
+            "ansi16" => Some(TermProfile::Ansi16),
+            "ansi256" => Some(TermProfile::Ansi256),
+            "truecolor" => Some(TermProfile::TrueColor),
+            _ => None,
+        }
+    }
+
+    // Returns true if NO_COLOR is set or CLICOLOR is set to never, mirroring git's color config
+    // values.
+    fn colors_disabled(&self) -> bool {
+        self.no_color.is_truthy() || self.clicolor.value() == "never"
+    }
+
+    // Returns true if CLICOLOR enables colors when the output is a terminal. always and auto mirror
+    // git's color config values.
+    fn clicolor_enabled(&self) -> bool {
+        self.clicolor.is_truthy() || matches!(self.clicolor.value().as_str(), "always" | "auto")
+    }
+
+    // CLICOLOR_FORCE takes precedence, but an unrecognized value (ex: auto) is treated as unset
+    // so it doesn't hide a valid FORCE_COLOR value
+    fn force_color(&self) -> &TermVar {
+        if Self::is_valid_force_color(&self.clicolor_force) {
+            &self.clicolor_force
+        } else {
+            &self.force_color
+        }
+    }
+
+    fn is_valid_force_color(var: &TermVar) -> bool {
+        var.is_truthy()
+            || var.is_set_empty()
+            || var.value() == "always"
+            || Self::force_color_level(&var.value()).is_some()
+    }
+
+    // always mirrors git's color config values. An empty value also enables colors, following the
+    // supports-color convention. auto isn't handled here since it defers to the normal detection
+    // logic.
+    fn color_forced(&self) -> bool {
+        let force_color = self.force_color();
+        force_color.is_truthy()
+            || force_color.is_set_empty()
+            || force_color.value() == "always"
+            || self.clicolor.value() == "always"
+    }
+
+    fn forced_level(&self) -> Option<TermProfile> {
+        Self::force_color_level(&self.force_color().value())
+    }
+
+    fn force_color_level(value: &str) -> Option<TermProfile> {
+        match value {
+            // never mirrors git's color config values
+            "no_color" | "never" => Some(TermProfile::NoColor),
+            "ansi8" => Some(TermProfile::Ansi8),
+            "ansi" | "ansi16" => Some(TermProfile::Ansi16),
+            // Numeric levels follow chalk's conventions. 1 is treated as a truthy value.
+            "ansi256" | "2" => Some(TermProfile::Ansi256),
+            "truecolor" | "true_color" | "3" => Some(TermProfile::TrueColor),
+            _ => None,
         }
     }
 }
@@ -474,17 +1087,27 @@ impl TmuxVars {
                 .stderr(Stdio::piped())
                 .spawn()?;
             cmd.wait()?;
-            let mut out = String::new();
-            cmd.stdout
-                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "stdout missing"))?
-                .read_to_string(&mut out)?;
-            out
+            Self::read_info(
+                cmd.stdout
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "stdout missing"))?,
+            )?
         } else {
             String::new()
         };
 
         Ok(Self { tmux_info, tmux })
     }
+
+    // The output may not be valid UTF-8 with some locales. We only search for ASCII substrings, so
+    // replacing invalid sequences is harmless.
+    pub(crate) fn read_info<R>(mut reader: R) -> io::Result<String>
+    where
+        R: Read,
+    {
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
 }
 
 impl WindowsVars {
@@ -535,15 +1158,66 @@ pub struct DetectorSettings<T> {
     pub(crate) enable_query: bool,
     pub(crate) enable_terminfo: bool,
     pub(crate) enable_tmux_info: bool,
+    pub(crate) background: Option<Rgb>,
+    pub(crate) terminfo_override: Option<(Option<i32>, Option<bool>)>,
+    pub(crate) total_budget: Option<Duration>,
+    pub(crate) options: DetectorOptions,
+    pub(crate) force_is_terminal: Option<bool>,
+    pub(crate) probe_color: Option<Rgb>,
     pub(crate) query_terminal: T,
 }
 
+#[cfg(feature = "color-cache")]
+impl<T> DetectorSettings<T>
+where
+    T: QueryTerminal,
+{
+    // Copies the settings with terminal queries, terminfo, and tmux info disabled so only the
+    // environment variables are read
+    fn without_probes(&self) -> DetectorSettings<NoTerminal> {
+        DetectorSettings {
+            enable_query: false,
+            enable_terminfo: false,
+            enable_tmux_info: false,
+            background: self.background,
+            terminfo_override: self.terminfo_override,
+            total_budget: self.total_budget,
+            options: self.options,
+            force_is_terminal: self.force_is_terminal,
+            probe_color: self.probe_color,
+            query_terminal: NoTerminal,
+        }
+    }
+
+    // Hashes the settings that can change the detected profile
+    fn hash_cache_key<H>(&self, state: &mut H)
+    where
+        H: std::hash::Hasher,
+    {
+        use std::hash::Hash;
+
+        self.enable_query.hash(state);
+        self.enable_terminfo.hash(state);
+        self.enable_tmux_info.hash(state);
+        self.terminfo_override.hash(state);
+        self.options.hash(state);
+        self.force_is_terminal.hash(state);
+        self.probe_color.hash(state);
+    }
+}
+
 impl Default for DetectorSettings<NoTerminal> {
     fn default() -> Self {
         Self {
             enable_query: false,
             enable_terminfo: true,
             enable_tmux_info: true,
+            background: None,
+            terminfo_override: None,
+            total_budget: None,
+            options: DetectorOptions::default(),
+            force_is_terminal: None,
+            probe_color: None,
             query_terminal: NoTerminal,
         }
     }
@@ -564,14 +1238,137 @@ impl<T> DetectorSettings<T> {
         self
     }
 
+    /// Enable or disable trusting the non-standard `Tc`/`RGB` terminfo extensions. If disabled,
+    /// only the max colors property will be used from the terminfo entry. This is useful if a
+    /// terminfo entry incorrectly reports true color support.
+    #[cfg(feature = "terminfo")]
+    pub fn trust_terminfo_truecolor(mut self, trust_terminfo_truecolor: bool) -> Self {
+        self.options.ignore_terminfo_truecolor = !trust_terminfo_truecolor;
+        self
+    }
+
     /// Enable or disable querying the tmux information if tmux is used.
     pub fn enable_tmux_info(mut self, enable_tmux_info: bool) -> Self {
         self.enable_tmux_info = enable_tmux_info;
         self
     }
+
+    /// Use the given terminfo properties instead of querying the terminfo database. This is
+    /// useful if the terminfo database isn't available, but the properties are known from
+    /// another source.
+    pub fn terminfo_override(mut self, max_colors: Option<i32>, truecolor: Option<bool>) -> Self {
+        self.terminfo_override = Some((max_colors, truecolor));
+        self
+    }
+
+    /// Sets the color that's used to probe the terminal for true color support. The terminal's
+    /// background is set to this color and then read back, so true color is detected if the
+    /// terminal reports the same color. This can be useful for debugging if the default color
+    /// isn't read back correctly.
+    #[cfg(feature = "query-detect")]
+    pub fn probe_color(mut self, probe_color: Rgb) -> Self {
+        self.probe_color = Some(probe_color);
+        self
+    }
+
+    /// Sets the maximum amount of time to spend probing the terminal. Once the budget is
+    /// exceeded, any remaining probes (terminal query, tmux info, and terminfo) are skipped and
+    /// detection falls back to using environment variables.
+    pub fn total_budget(mut self, total_budget: Duration) -> Self {
+        self.total_budget = Some(total_budget);
+        self
+    }
+
+    /// Assume the terminal supports 256 colors if `TERM` is set to a value that isn't
+    /// recognized and has no terminfo entry. Many newer terminals support at least 256 colors, so
+    /// this trades caution for better defaults on modern terminals. If the terminfo database is
+    /// disabled, any unrecognized `TERM` value is treated this way. The default value is `false`.
+    pub fn optimistic(mut self, optimistic: bool) -> Self {
+        self.options.optimistic = optimistic;
+        self
+    }
+
+    /// Only enable true color support from `COLORTERM` if it's set to `24bit` or `truecolor`.
+    /// By default, any truthy value such as `COLORTERM=1` also enables true color support, which
+    /// may be incorrect in some environments. The default value is `false`.
+    pub fn strict_colorterm(mut self, strict_colorterm: bool) -> Self {
+        self.options.strict_colorterm = strict_colorterm;
+        self
+    }
+
+    /// Enable true color support from `COLORTERM` when running in `screen`. Newer versions of
+    /// `screen` (5.0+) support true color, but it must be enabled explicitly and there's no
+    /// reliable way to detect whether it was, so `COLORTERM` is ignored in `screen` by default.
+    /// Enable this if your `screen` configuration is known to support true color. The default
+    /// value is `false`.
+    pub fn screen_truecolor(mut self, screen_truecolor: bool) -> Self {
+        self.options.screen_truecolor = screen_truecolor;
+        self
+    }
+
+    /// Limit the profile detected from the terminal variables when running in a multiplexer
+    /// such as `screen` or `tmux`. Multiplexers usually support 256 colors, but some
+    /// environments, such as monochrome serial consoles, set `TERM=screen` without supporting
+    /// any color. This doesn't affect explicit overrides like `FORCE_COLOR`.
+    pub fn multiplexer_cap(mut self, max_profile: TermProfile) -> Self {
+        self.options.multiplexer_cap = Some(max_profile);
+        self
+    }
+
+    /// Overrides whether the output is a terminal. If set to [`Some`], this value is used instead
+    /// of the result from [`IsTerminal::is_terminal`]. This is useful when the TTY status is known
+    /// from another source, such as a pty that's managed by the application. `TTY_FORCE` still
+    /// takes precedence over this setting.
+    pub fn force_is_terminal(mut self, force_is_terminal: Option<bool>) -> Self {
+        self.force_is_terminal = force_is_terminal;
+        self
+    }
+
+    /// Treat a `dumb` terminal as [`TermProfile::NoColor`] instead of [`TermProfile::NoTty`].
+    /// This allows text modifiers to be emitted to a `dumb` terminal. The default value is
+    /// `false`.
+    pub fn dumb_as_no_color(mut self, dumb_as_no_color: bool) -> Self {
+        self.options.dumb_as_no_color = dumb_as_no_color;
+        self
+    }
+
+    /// Sets a known background color for the terminal. This is stored in
+    /// [`TermMetaVars::background`] so it can be used without querying the terminal again. Load
+    /// the variables with [`TermVars::from_env`] and detect the profile with
+    /// [`TermProfile::detect_with_vars`] to keep access to it after detection.
+    pub fn background(mut self, background: Rgb) -> Self {
+        self.background = Some(background);
+        self
+    }
 }
 
 impl TermProfile {
+    /// Returns the profile for the given `TERM` value using only the terminfo database and the
+    /// `TERM` heuristics used during detection. This is useful for classifying terminals offline
+    /// since no environment variables are read and the output is assumed to be a terminal.
+    ///
+    /// If there's no terminfo entry for the name, only the `TERM` heuristics are used.
+    #[cfg(feature = "terminfo")]
+    pub fn from_terminfo_name(term: &str) -> Self {
+        let terminfo = termini::TermInfo::from_name(term)
+            .map(|info| TerminfoVars::from_terminfo(&info))
+            .unwrap_or_default();
+        Self::from_term_and_terminfo(term, terminfo)
+    }
+
+    #[cfg(feature = "terminfo")]
+    fn from_term_and_terminfo(term: &str, terminfo: TerminfoVars) -> Self {
+        Self::detect_with_vars(TermVars {
+            meta: TermMetaVars {
+                is_terminal: true,
+                term: TermVar::new(term),
+                ..Default::default()
+            },
+            terminfo,
+            ..Default::default()
+        })
+    }
+
     /// Detect the output's profile information.
     ///
     /// This is a potentially expensive operation depending on the settings and features enabled.
@@ -584,6 +1381,61 @@ impl TermProfile {
         Self::detect_with_vars(TermVars::from_env(output, settings))
     }
 
+    /// Detect the profile information for [`stdout`](io::stdout). This is a shorthand for
+    /// [`detect`](Self::detect) using [`io::stdout`].
+    ///
+    /// Make sure to use the profile detected from the same stream that you write to, since
+    /// stdout and stderr may be redirected separately.
+    ///
+    /// ```
+    /// use termprofile::{DetectorSettings, TermProfile};
+    ///
+    /// let profile = TermProfile::detect_stdout(DetectorSettings::default());
+    /// println!("{profile:?}");
+    /// ```
+    pub fn detect_stdout<Q>(settings: DetectorSettings<Q>) -> Self
+    where
+        Q: QueryTerminal,
+    {
+        Self::detect(&io::stdout(), settings)
+    }
+
+    /// Detect the profile information for [`stderr`](io::stderr). This is a shorthand for
+    /// [`detect`](Self::detect) using [`io::stderr`].
+    ///
+    /// ```
+    /// use termprofile::{DetectorSettings, TermProfile};
+    ///
+    /// let profile = TermProfile::detect_stderr(DetectorSettings::default());
+    /// eprintln!("{profile:?}");
+    /// ```
+    pub fn detect_stderr<Q>(settings: DetectorSettings<Q>) -> Self
+    where
+        Q: QueryTerminal,
+    {
+        Self::detect(&io::stderr(), settings)
+    }
+
+    /// Determine the profile using only the override variables (`NO_COLOR`, `COLOR_PROFILE`,
+    /// `FORCE_COLOR`, and `CLICOLOR_FORCE`).
+    ///
+    /// Returns [`None`] if no override decisively sets the profile, such as when `FORCE_COLOR`
+    /// is only set to a truthy value and the final profile depends on the terminal. This is
+    /// cheap to run, so it can be used to skip more expensive detection steps. Note that this
+    /// doesn't check whether the output is a terminal.
+    pub fn from_overrides<S>(source: &S) -> Option<Self>
+    where
+        S: EnvVarSource,
+    {
+        let overrides = OverrideVars::from_source(source);
+        if overrides.colors_disabled() {
+            return Some(Self::NoColor);
+        }
+        overrides
+            .color_profile_level()
+            .or_else(|| overrides.forced_level())
+    }
+
     /// Detect the output's profile information using the given variables as the source.
     ///
     /// This is a potentially expensive operation depending on the settings and features enabled.
@@ -591,13 +1443,20 @@ impl TermProfile {
     pub fn detect_with_vars(vars: TermVars) -> Self {
         let detector = Detector { vars };
         let profile = detector.detect_tty();
-        if let Some(env) = detector.detect_no_color()
+        let no_color = detector.detect_no_color();
+        if let Some(env) = no_color
             && profile > Self::NoTty
         {
             return env;
         }
+        // The explicit overrides bypass the TTY check, but NO_COLOR should still prevent them from
+        // enabling colors.
+        let max_profile = no_color.unwrap_or(Self::TrueColor);
+        if let Some(env) = detector.detect_color_profile() {
+            return env.min(max_profile);
+        }
         if let Some(env) = detector.detect_force_color() {
-            return env;
+            return env.min(max_profile);
         }
         if detector.vars.meta.dcs_response {
             return Self::TrueColor;
@@ -608,11 +1467,95 @@ impl TermProfile {
         if profile == Self::NoTty {
             return profile;
         }
+        if detector.vars.meta.is_dumb() {
+            return Self::NoColor;
+        }
 
         detector.detect_term_vars()
     }
 }
 
+#[cfg(feature = "color-cache")]
+static DETECTION_CACHE: std::sync::LazyLock<std::sync::Mutex<lru::LruCache<u64, TermProfile>>> =
+    std::sync::LazyLock::new(|| {
+        std::sync::Mutex::new(lru::LruCache::new(
+            std::num::NonZeroUsize::new(16).expect("non-zero"),
+        ))
+    });
+
+#[cfg(feature = "color-cache")]
+impl TermProfile {
+    /// Detect the profile using the variables from the given source, reusing the result from a
+    /// previous call if the environment and settings are the same.
+    ///
+    /// Results are stored in a small LRU cache keyed by a hash of the environment variables that
+    /// are read during detection, the detection options, and whether the output is a terminal.
+    /// This is useful for processes that repeatedly detect profiles for a small set of
+    /// environments, such as a daemon that spawns workers with different environments. Terminal
+    /// queries, terminfo, and tmux info are only loaded if there's no cached result.
+    ///
+    /// # Panics
+    ///
+    /// If the lock on the cache is poisoned
+    pub fn detect_cached_by_env<S, T, Q>(
+        source: &S,
+        output: &T,
+        settings: DetectorSettings<Q>,
+    ) -> Self
+    where
+        S: EnvVarSource,
+        T: IsTerminal,
+        Q: QueryTerminal,
+    {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        // Reading the environment variables is cheap, so load them without running any probes to
+        // find out which ones the detector uses
+        let recorder = RecordingSource {
+            source,
+            vars: std::cell::RefCell::default(),
+        };
+        let mut vars = TermVars::from_source(&recorder, output, settings.without_probes());
+
+        let mut hasher = DefaultHasher::new();
+        recorder.vars.into_inner().hash(&mut hasher);
+        output.is_terminal().hash(&mut hasher);
+        settings.hash_cache_key(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(profile) = DETECTION_CACHE.lock().expect("lock poisoned").get(&key) {
+            return *profile;
+        }
+        vars.load_probes(source, output, settings);
+        let profile = Self::detect_with_vars(vars);
+        DETECTION_CACHE
+            .lock()
+            .expect("lock poisoned")
+            .put(key, profile);
+        profile
+    }
+}
+
+#[cfg(feature = "color-cache")]
+struct RecordingSource<'a, S> {
+    source: &'a S,
+    vars: std::cell::RefCell<BTreeMap<String, Option<String>>>,
+}
+
+#[cfg(feature = "color-cache")]
+impl<S> EnvVarSource for RecordingSource<'_, S>
+where
+    S: EnvVarSource,
+{
+    fn var(&self, key: &str) -> Option<String> {
+        let value = self.source.var(key);
+        self.vars
+            .borrow_mut()
+            .insert(key.to_string(), value.clone());
+        value
+    }
+}
+
 struct Detector {
     vars: TermVars,
 }
@@ -620,7 +1563,7 @@ struct Detector {
 impl Detector {
     fn detect_tty(&self) -> TermProfile {
         if (!self.vars.overrides.tty_force.is_truthy() && !self.vars.meta.is_terminal)
-            || self.vars.meta.is_dumb()
+            || (self.vars.meta.is_dumb() && !self.vars.options.dumb_as_no_color)
         {
             TermProfile::NoTty
         } else {
@@ -628,34 +1571,25 @@ impl Detector {
         }
     }
     fn detect_no_color(&self) -> Option<TermProfile> {
-        if self.vars.overrides.no_color.is_truthy() {
+        if self.vars.overrides.colors_disabled() {
             Some(TermProfile::NoColor)
         } else {
             None
         }
     }
 
-    fn detect_force_color(&self) -> Option<TermProfile> {
-        let mut profile = None;
-        let force_color = self
-            .vars
-            .overrides
-            .clicolor_force
-            .or(&self.vars.overrides.force_color);
-
-        if force_color.is_truthy() {
-            profile = profile.max(Some(TermProfile::Ansi16));
-        }
-
-        match force_color.value().as_str() {
-            "no_color" => return Some(TermProfile::NoColor),
-            "ansi" | "ansi16" => return Some(TermProfile::Ansi16),
-            "ansi256" => return Some(TermProfile::Ansi256),
-            "truecolor" | "true_color" => return Some(TermProfile::TrueColor),
-            _ => {}
-        };
+    fn detect_color_profile(&self) -> Option<TermProfile> {
+        self.vars.overrides.color_profile_level()
+    }
 
-        profile.map(|p| p.max(self.detect_term_vars()))
+    fn detect_force_color(&self) -> Option<TermProfile> {
+        if let Some(profile) = self.vars.overrides.forced_level() {
+            return Some(profile);
+        }
+        if self.vars.overrides.color_forced() {
+            return Some(TermProfile::Ansi16.max(self.detect_term_vars()));
+        }
+        None
     }
 
     fn detect_special_cases(&self) -> Option<TermProfile> {
@@ -701,13 +1635,26 @@ impl Detector {
     }
 
     fn detect_term_vars(&self) -> TermProfile {
+        let profile = self.detect_term_vars_uncapped();
+        // A terminfo entry that explicitly sets colors#0 doesn't support color, regardless of
+        // what the TERM name implies. A missing entry is treated as unknown instead.
+        if self.vars.terminfo.max_colors == Some(0) {
+            return profile.min(TermProfile::NoColor);
+        }
+        match self.vars.options.multiplexer_cap {
+            Some(cap) if self.vars.multiplexer().is_some() => profile.min(cap),
+            _ => profile,
+        }
+    }
+
+    fn detect_term_vars_uncapped(&self) -> TermProfile {
         let colorterm = self.vars.meta.colorterm.value();
         let mut term = self.vars.meta.term.value();
-        let term_program = self.vars.meta.term_program.value();
+        let (term_program, term_program_version) = self.vars.meta.term_program();
 
         let mut profile = TermProfile::NoColor;
 
-        if term.is_empty() && !self.vars.overrides.clicolor.is_truthy() {
+        if term.is_empty() && !self.vars.overrides.clicolor_enabled() {
             if let Some(win_profile) = self.detect_windows() {
                 profile = win_profile;
             }
@@ -716,16 +1663,12 @@ impl Detector {
         }
 
         match term_program.as_str() {
-            "mintty" => {
+            MINTTY => {
                 // Supported as of 2015: https://github.com/mintty/mintty/commit/8e1f4c260b5e1b3311caf10e826d87c85b3c9433
                 return TermProfile::TrueColor;
             }
-            "iterm.app" => {
-                let term_program_version = self
-                    .vars
-                    .meta
-                    .term_program_version
-                    .value()
+            ITERM => {
+                let term_program_version = term_program_version
                     .split(".")
                     .next()
                     .and_then(|v| v.parse::<u32>().ok())
@@ -736,26 +1679,47 @@ impl Detector {
                     return TermProfile::Ansi256;
                 }
             }
-            "apple_terminal" => return TermProfile::Ansi256,
+            APPLE_TERMINAL => return TermProfile::Ansi256,
+            VSCODE => return TermProfile::TrueColor,
             _ => {}
         }
 
+        // PuTTY supports 256 colors, but the forwarded TERM value may report otherwise
+        if !self.vars.meta.putty.is_empty() || prefix_or_equal(&term, PUTTY_TERM) {
+            return TermProfile::Ansi256;
+        }
+
         let mut is_screen = false;
+        let mut recognized = false;
+        // Screen only supports 256 colors if the host terminal does, so this is determined by the
+        // underlying TERM value or terminfo entry instead of assuming support
         if prefix_or_equal(&term, SCREEN) {
             term = term.replacen("screen.", "", 1);
             is_screen = true;
-            profile = profile.max(TermProfile::Ansi256);
+            recognized = true;
+        }
+        // Terminal names may be embedded anywhere in the TERM value (ex: foot-extra-256color)
+        if term.split("-").any(|t| TRUECOLOR_TERMS.contains(&t)) {
+            return TermProfile::TrueColor;
+        }
+        if ANSI8_TERMS.contains(&term.as_str()) {
+            return TermProfile::Ansi8;
+        }
+        // These never support more than 16 colors, so a stray COLORTERM shouldn't promote them
+        if ANSI_SYS_TERMS.contains(&term.as_str()) {
+            return TermProfile::Ansi16;
         }
         let term_last = term.split("-").last().unwrap_or_default();
         match term_last {
-            "alacritty" | "contour" | "rio" | "wezterm" | "ghostty" | "kitty" | "foot" | "st"
-            | "direct" => {
-                return TermProfile::TrueColor;
-            }
-            "256color" => {
+            // Some terminfo entries use this instead of the standard direct suffix
+            "truecolor" => return TermProfile::TrueColor,
+            // There's no 88 color profile, but clamping down to 16 colors would be strictly worse
+            "256color" | "88color" => {
+                recognized = true;
                 profile = profile.max(TermProfile::Ansi256);
             }
             "linux" | "xterm" => {
+                recognized = true;
                 profile = profile.max(TermProfile::Ansi16);
             }
             _ => {}
@@ -764,24 +1728,34 @@ impl Detector {
         // tmux changes the TERM variable which could make this report 256 color or truecolor
         // incorrectly
         if let Some(tmux_profile) = self.detect_tmux() {
+            recognized = true;
             profile = profile.max(tmux_profile);
         }
 
         // New versions of screen do support truecolor, but it must be enabled explicitly and
-        // there doesn't appear to be an easy way to detect this.
+        // there doesn't appear to be an easy way to detect this, so it's opt-in.
         if (matches!(colorterm.as_str(), "24bit" | "truecolor")
-            || self.vars.meta.colorterm.is_truthy())
-            && !is_screen
-            && !self.is_tmux()
+            || (self.vars.meta.colorterm.is_truthy() && !self.vars.options.strict_colorterm))
+            && (!is_screen || self.vars.options.screen_truecolor)
+            && !self.vars.is_tmux()
         {
             return TermProfile::TrueColor;
         }
+        // Some terminals report their bit depth instead. 16 bit "high color" has no equivalent
+        // profile, so it's treated as the closest one that doesn't overstate support.
+        if matches!(colorterm.as_str(), "8bit" | "16bit") {
+            recognized = true;
+            profile = profile.max(TermProfile::Ansi256);
+        }
 
         if term.contains("color") || term.contains("ansi") {
+            recognized = true;
             profile = profile.max(TermProfile::Ansi16);
         }
 
-        if self.vars.terminfo.truecolor == Some(true) {
+        if self.vars.terminfo.truecolor == Some(true)
+            && !self.vars.options.ignore_terminfo_truecolor
+        {
             return TermProfile::TrueColor;
         }
 
@@ -790,31 +1764,41 @@ impl Detector {
         if terminfo_colors >= TERMINFO_MAX_COLORS {
             return TermProfile::TrueColor;
         }
-        if terminfo_colors >= 256 {
+        if terminfo_colors >= 88 {
             profile = profile.max(TermProfile::Ansi256);
         }
+        // Terminfo reports 8 colors for some terminals that do support bright colors, such as
+        // xterm, so this is only trusted if the TERM value wasn't recognized
+        if terminfo_colors == 8
+            && !recognized
+            && !BRIGHT_TERM_PREFIXES.iter().any(|t| term.starts_with(t))
+            && profile == TermProfile::Ansi16
+        {
+            profile = TermProfile::Ansi8;
+        }
 
-        profile
-    }
+        // An entry without a colors capability is a monochrome terminal, so this only applies if
+        // there's no entry at all
+        if self.vars.options.optimistic
+            && !term.is_empty()
+            && !is_screen
+            && !recognized
+            && !self.vars.terminfo.found
+        {
+            profile = profile.max(TermProfile::Ansi256);
+        }
 
-    fn is_tmux(&self) -> bool {
-        !self.vars.tmux.tmux.is_empty()
-            || prefix_or_equal(&self.vars.meta.term.value(), TMUX)
-            || prefix_or_equal(&self.vars.meta.term_program.value(), TMUX)
+        profile
     }
 
     fn detect_tmux(&self) -> Option<TermProfile> {
-        if !self.is_tmux() {
-            return None;
-        }
-
-        let tmux_info = self.vars.tmux.tmux_info.split("\n");
-        for line in tmux_info {
-            if (line.contains(TC) || line.contains(RGB)) && line.contains("true") {
-                return Some(TermProfile::TrueColor);
+        self.vars.tmux_truecolor().map(|truecolor| {
+            if truecolor {
+                TermProfile::TrueColor
+            } else {
+                TermProfile::Ansi256
             }
-        }
-        Some(TermProfile::Ansi256)
+        })
     }
 
     fn detect_windows(&self) -> Option<TermProfile> {
@@ -856,6 +1840,132 @@ impl Detector {
     }
 }
 
+impl TermVars {
+    /// Returns a human-readable report of every variable along with the profile detected from
+    /// them, suitable for attaching to bug reports. Variables are grouped by category and the
+    /// layout is stable across runs, so reports can be compared directly.
+    ///
+    /// Values are shown after normalization, so environment variables will be lowercased and
+    /// trimmed.
+    pub fn debug_report(&self) -> String {
+        let mut report = DebugReport::default();
+        report.line(
+            "profile",
+            format_args!("{:?}", TermProfile::detect_with_vars(self.clone())),
+        );
+
+        let overrides = &self.overrides;
+        report.section("overrides");
+        report.var(NO_COLOR, &overrides.no_color);
+        report.var(FORCE_COLOR, &overrides.force_color);
+        report.var(CLICOLOR, &overrides.clicolor);
+        report.var(CLICOLOR_FORCE, &overrides.clicolor_force);
+        report.var(TTY_FORCE, &overrides.tty_force);
+        report.var(COLOR_PROFILE, &overrides.color_profile);
+
+        let meta = &self.meta;
+        report.section("meta");
+        report.line("is_terminal", meta.is_terminal);
+        report.var(TERM, &meta.term);
+        report.var(COLORTERM, &meta.colorterm);
+        report.var(TERM_PROGRAM, &meta.term_program);
+        report.var(TERM_PROGRAM_VERSION, &meta.term_program_version);
+        report.var(LC_TERMINAL, &meta.lc_terminal);
+        report.var(LC_TERMINAL_VERSION, &meta.lc_terminal_version);
+        report.var(ZELLIJ, &meta.zellij);
+        report.var(PUTTY, &meta.putty);
+        report.var(VSCODE_INJECTION, &meta.vscode_injection);
+        report.var(SSH_CONNECTION, &meta.ssh_connection);
+        report.var(SSH_CLIENT, &meta.ssh_client);
+        report.var(SSH_TTY, &meta.ssh_tty);
+        report.line("dcs_response", meta.dcs_response);
+        report.line("kitty_graphics", meta.kitty_graphics);
+        report.line("background", format_args!("{:?}", meta.background));
+
+        let special = &self.special;
+        report.section("special");
+        report.var("GOOGLE_CLOUD_SHELL", &special.google_cloud_shell);
+        report.var("GITHUB_ACTIONS", &special.github_actions);
+        report.var("GITEA_ACTIONS", &special.gitea_actions);
+        report.var("CIRCLECI", &special.circleci);
+        report.var("TRAVIS", &special.travis);
+        report.var("APPVEYOR", &special.appveyor);
+        report.var("GITLAB_CI", &special.gitlab_ci);
+        report.var("BUILDKITE", &special.buildkite);
+        report.var("DRONE", &special.drone);
+        report.var("TEAMCITY_VERSION", &special.teamcity_version);
+        report.var("TF_BUILD", &special.tf_build);
+        report.var("AGENT_NAME", &special.agent_name);
+        report.var("CIRRUS_CI", &special.cirrus_ci);
+        report.var("CI_NAME", &special.ci_name);
+        report.var("ConEmuANSI", &special.con_emu_ansi);
+        report.var("CI", &special.ci);
+
+        report.section("tmux");
+        report.var("TMUX", &self.tmux.tmux);
+        report.line("tmux_info", format_args!("{:?}", self.tmux.tmux_info));
+
+        let windows = &self.windows;
+        report.section("windows");
+        report.line("is_windows", windows.is_windows);
+        report.line("os_version", windows.os_version);
+        report.line("build_number", windows.build_number);
+        report.var("ANSICON", &windows.ansicon);
+        report.var("ANSICON_VER", &windows.ansicon_ver);
+
+        let terminfo = &self.terminfo;
+        report.section("terminfo");
+        report.line("max_colors", format_args!("{:?}", terminfo.max_colors));
+        report.line("truecolor", format_args!("{:?}", terminfo.truecolor));
+        report.line("found", terminfo.found);
+
+        let options = &self.options;
+        report.section("options");
+        report.line("dumb_as_no_color", options.dumb_as_no_color);
+        report.line("optimistic", options.optimistic);
+        report.line(
+            "multiplexer_cap",
+            format_args!("{:?}", options.multiplexer_cap),
+        );
+        report.line("strict_colorterm", options.strict_colorterm);
+        report.line("screen_truecolor", options.screen_truecolor);
+        report.line(
+            "ignore_terminfo_truecolor",
+            options.ignore_terminfo_truecolor,
+        );
+
+        report.out
+    }
+}
+
+#[derive(Default)]
+struct DebugReport {
+    out: String,
+    indent: &'static str,
+}
+
+impl DebugReport {
+    fn section(&mut self, name: &str) {
+        self.out.push_str(&format!("\n[{name}]\n"));
+        self.indent = "  ";
+    }
+
+    fn line<V>(&mut self, name: &str, value: V)
+    where
+        V: fmt::Display,
+    {
+        self.out
+            .push_str(&format!("{}{name}: {value}\n", self.indent));
+    }
+
+    fn var(&mut self, name: &str, var: &TermVar) {
+        match &var.0 {
+            Some(value) => self.line(name, format_args!("{value:?}")),
+            None => self.line(name, "<unset>"),
+        }
+    }
+}
+
 /// Represents an environment variable.
 #[derive(Clone, Debug, Default)]
 pub struct TermVar(Option<String>);
@@ -893,11 +2003,11 @@ impl TermVar {
         self.0.as_deref().map(str::is_empty).unwrap_or(true)
     }
 
-    fn or(&self, other: &Self) -> Self {
-        Self(self.0.clone().or_else(|| other.0.clone()))
+    pub(crate) fn is_set_empty(&self) -> bool {
+        self.0.as_deref() == Some("")
     }
 
-    fn value(&self) -> String {
+    pub(crate) fn value(&self) -> String {
         self.0.clone().unwrap_or_default()
     }
 }
@@ -1,10 +1,15 @@
 use std::collections::{HashMap, VecDeque};
-use std::io;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{io, thread};
 
 use rstest::rstest;
 
-use super::{IsTerminal, TermVar, TermVars};
-use crate::{DcsEvent, DetectorSettings, QueryTerminal, Rgb, TermProfile, WindowsVars};
+use super::{IsTerminal, TermMetaVars, TermVar, TermVars, TmuxVars};
+use crate::{
+    DcsEvent, DetectorSettings, Multiplexer, ParseRgbError, QueryTerminal, RecordingTerminal, Rgb,
+    TermProfile, WindowsVars,
+};
 
 #[test]
 fn default_terminal() {
@@ -65,6 +70,26 @@ fn no_color_precedence() {
     assert_eq!(TermProfile::NoColor, support);
 }
 
+#[rstest]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "1")], true)]
+#[case(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "ansi256")], true)]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "")], true)]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "0")], false)]
+#[case(&[("CLICOLOR", "never"), ("FORCE_COLOR", "1")], true)]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "never")], false)]
+#[case(&[("NO_COLOR", "1"), ("COLOR_PROFILE", "truecolor")], true)]
+#[case(&[("NO_COLOR", "1"), ("COLOR_PROFILE", "nocolor")], false)]
+#[case(&[("NO_COLOR", "1"), ("CLICOLOR", "always")], true)]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "ansi8")], true)]
+#[case(&[("CLICOLOR", "never"), ("COLOR_PROFILE", "ansi256")], true)]
+#[case(&[("NO_COLOR", "1")], false)]
+#[case(&[("FORCE_COLOR", "1")], false)]
+#[case(&[("COLOR_PROFILE", "truecolor")], false)]
+fn conflicting_overrides(#[case] env: &[(&str, &str)], #[case] conflict: bool) {
+    let vars = make_vars(&ForceTerminal, env);
+    assert_eq!(conflict, vars.overrides.conflicting_overrides());
+}
+
 #[test]
 fn force_color() {
     let vars = make_vars(&ForceNoTerminal, &[("FORCE_COLOR", "1")]);
@@ -89,6 +114,44 @@ fn clicolor_force() {
     assert_eq!(TermProfile::Ansi16, support);
 }
 
+#[test]
+fn clicolor_force_always() {
+    let vars = make_vars(&ForceNoTerminal, &[("CLICOLOR_FORCE", "always")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[test]
+fn clicolor_force_never() {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("CLICOLOR_FORCE", "never"), ("TERM", "xterm-256color")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoColor, support);
+}
+
+#[rstest]
+#[case("auto", "3", TermProfile::TrueColor)]
+#[case("auto", "1", TermProfile::Ansi16)]
+#[case("0", "ansi256", TermProfile::Ansi256)]
+#[case("2", "3", TermProfile::Ansi256)]
+fn clicolor_force_invalid(
+    #[case] clicolor_force: &str,
+    #[case] force_color: &str,
+    #[case] expected: TermProfile,
+) {
+    let vars = make_vars(
+        &ForceNoTerminal,
+        &[
+            ("CLICOLOR_FORCE", clicolor_force),
+            ("FORCE_COLOR", force_color),
+        ],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
 #[test]
 fn force_color_disabled() {
     let vars = make_vars(
@@ -113,6 +176,19 @@ fn force_color_level_truthy() {
     assert_eq!(TermProfile::Ansi16, support);
 }
 
+#[rstest]
+#[case("FORCE_COLOR")]
+#[case("CLICOLOR_FORCE")]
+fn force_color_empty(#[case] var: &str) {
+    let vars = make_vars(&ForceNoTerminal, &[(var, "")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+
+    let vars = make_vars(&ForceTerminal, &[(var, ""), ("TERM", "xterm-256color")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi256, support);
+}
+
 #[test]
 fn force_color_level_ansi_basic() {
     let vars = make_vars(&ForceNoTerminal, &[("FORCE_COLOR", "ansi")]);
@@ -134,6 +210,23 @@ fn force_color_truecolor() {
     assert_eq!(TermProfile::TrueColor, support);
 }
 
+#[rstest]
+#[case("FORCE_COLOR", "2", TermProfile::Ansi256)]
+#[case("FORCE_COLOR", "3", TermProfile::TrueColor)]
+#[case("CLICOLOR_FORCE", "2", TermProfile::Ansi256)]
+#[case("CLICOLOR_FORCE", "3", TermProfile::TrueColor)]
+#[case("CLICOLOR_FORCE", "truecolor", TermProfile::TrueColor)]
+#[case("FORCE_COLOR", "ansi8", TermProfile::Ansi8)]
+fn force_color_numeric_level(
+    #[case] var: &str,
+    #[case] value: &str,
+    #[case] expected: TermProfile,
+) {
+    let vars = make_vars(&ForceNoTerminal, &[(var, value)]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
 #[test]
 fn force_color_extended_override() {
     let vars = make_vars(
@@ -144,6 +237,63 @@ fn force_color_extended_override() {
     assert_eq!(TermProfile::Ansi256, support);
 }
 
+#[rstest]
+#[case("notty", TermProfile::NoTty)]
+#[case("nocolor", TermProfile::NoColor)]
+#[case("ansi8", TermProfile::Ansi8)]
+#[case("ansi16", TermProfile::Ansi16)]
+#[case("ansi256", TermProfile::Ansi256)]
+#[case("TrueColor", TermProfile::TrueColor)]
+#[case("invalid", TermProfile::Ansi256)]
+fn color_profile(#[case] value: &str, #[case] expected: TermProfile) {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[
+            ("COLOR_PROFILE", value),
+            ("TERM", "xterm-256color"),
+            ("FORCE_COLOR", "1"),
+        ],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case("truecolor", TermProfile::NoColor)]
+#[case("ansi16", TermProfile::NoColor)]
+#[case("notty", TermProfile::NoColor)]
+fn color_profile_no_color(#[case] value: &str, #[case] expected: TermProfile) {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("COLOR_PROFILE", value), ("NO_COLOR", "1")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case("truecolor", TermProfile::NoColor)]
+#[case("nocolor", TermProfile::NoColor)]
+#[case("notty", TermProfile::NoTty)]
+fn color_profile_no_color_no_tty(#[case] value: &str, #[case] expected: TermProfile) {
+    let vars = make_vars(
+        &ForceNoTerminal,
+        &[("COLOR_PROFILE", value), ("NO_COLOR", "1")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[test]
+fn force_color_no_color_no_tty() {
+    let vars = make_vars(
+        &ForceNoTerminal,
+        &[("FORCE_COLOR", "truecolor"), ("NO_COLOR", "1")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoColor, support);
+}
+
 #[test]
 fn clicolor_no_tty() {
     let vars = make_vars(&ForceNoTerminal, &[("CLICOLOR", "1")]);
@@ -158,6 +308,62 @@ fn clicolor_tty() {
     assert_eq!(TermProfile::Ansi16, support);
 }
 
+#[test]
+fn clicolor_always() {
+    let vars = make_vars(&ForceNoTerminal, &[("CLICOLOR", "always")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[test]
+fn clicolor_never() {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("CLICOLOR", "never"), ("TERM", "xterm-256color")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoColor, support);
+}
+
+#[rstest]
+#[case(&ForceTerminal, TermProfile::Ansi16)]
+#[case(&ForceNoTerminal, TermProfile::NoTty)]
+fn clicolor_auto<T: IsTerminal>(#[case] out: &T, #[case] expected: TermProfile) {
+    let vars = make_vars(out, &[("CLICOLOR", "auto")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case("CLICOLOR_FORCE")]
+#[case("FORCE_COLOR")]
+fn force_color_auto(#[case] var: &str) {
+    let vars = make_vars(&ForceNoTerminal, &[(var, "auto")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoTty, support);
+
+    let vars = make_vars(&ForceTerminal, &[(var, "auto"), ("TERM", "xterm-256color")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi256, support);
+}
+
+#[test]
+fn force_color_always() {
+    let vars = make_vars(&ForceNoTerminal, &[("FORCE_COLOR", "always")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[test]
+fn force_color_never() {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("FORCE_COLOR", "never"), ("TERM", "xterm-256color")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoColor, support);
+}
+
 #[test]
 fn clicolor_override() {
     let vars = make_vars(
@@ -168,10 +374,94 @@ fn clicolor_override() {
     assert_eq!(TermProfile::Ansi256, support);
 }
 
+#[rstest]
+#[case("1", false, TermProfile::TrueColor)]
+#[case("1", true, TermProfile::Ansi256)]
+#[case("truecolor", true, TermProfile::TrueColor)]
+#[case("24bit", true, TermProfile::TrueColor)]
+fn strict_colorterm(#[case] colorterm: &str, #[case] strict: bool, #[case] expected: TermProfile) {
+    let mut vars = TermVars::from_source(
+        &HashMap::from_iter([("COLORTERM", colorterm), ("TERM", "xterm-256color")]),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .strict_colorterm(strict),
+    );
+    vars.windows = WindowsVars::default();
+    assert_eq!(expected, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case(false, TermProfile::Ansi256)]
+#[case(true, TermProfile::TrueColor)]
+fn screen_truecolor(#[case] enabled: bool, #[case] expected: TermProfile) {
+    let mut vars = TermVars::from_source(
+        &HashMap::from_iter([
+            ("TERM", "screen.xterm-256color"),
+            ("COLORTERM", "truecolor"),
+        ]),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .screen_truecolor(enabled),
+    );
+    vars.windows = WindowsVars::default();
+    assert_eq!(expected, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case(Some(true), &[], TermProfile::Ansi256)]
+#[case(Some(false), &[], TermProfile::NoTty)]
+#[case(None, &[], TermProfile::NoTty)]
+#[case(Some(false), &[("TTY_FORCE", "1")], TermProfile::Ansi256)]
+fn force_is_terminal(
+    #[case] is_terminal: Option<bool>,
+    #[case] env: &[(&str, &str)],
+    #[case] expected: TermProfile,
+) {
+    let mut source = HashMap::from_iter([("TERM", "xterm-256color")]);
+    source.extend(env.iter().copied());
+    let mut vars = TermVars::from_source(
+        &source,
+        &ForceNoTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .force_is_terminal(is_terminal),
+    );
+    vars.windows = WindowsVars::default();
+    assert_eq!(expected, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case("8bit", "xterm", TermProfile::Ansi256)]
+#[case("16bit", "xterm", TermProfile::Ansi256)]
+#[case("8bit", "", TermProfile::Ansi256)]
+#[case("8bit", "xterm-kitty", TermProfile::TrueColor)]
+#[case("24bit", "xterm", TermProfile::TrueColor)]
+fn colorterm_bit_depth(#[case] colorterm: &str, #[case] term: &str, #[case] expected: TermProfile) {
+    let vars = make_vars(&ForceTerminal, &[("COLORTERM", colorterm), ("TERM", term)]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
 #[rstest]
 #[case("alacritty")]
 #[case("wezterm")]
 #[case("xterm-kitty")]
+#[case("foot")]
+#[case("foot-extra")]
+#[case("foot-direct")]
+#[case("foot-extra-direct")]
+#[case("foot-extra-256color")]
+#[case("xterm-kitty-something")]
+#[case("st")]
+#[case("st-256color")]
+#[case("mlterm")]
+#[case("mlterm-256color")]
+#[case("xterm-truecolor")]
 fn truecolor_term(#[case] term: &str) {
     let vars = make_vars(&ForceTerminal, &[("TERM", term)]);
     let support = TermProfile::detect_with_vars(vars);
@@ -181,7 +471,8 @@ fn truecolor_term(#[case] term: &str) {
 #[rstest]
 #[case("xterm-256color")]
 #[case("screen.xterm-256color")]
-#[case("screen")]
+#[case("screen-256color")]
+#[case("rxvt-88color")]
 fn ansi256_term(#[case] term: &str) {
     let vars = make_vars(&ForceTerminal, &[("TERM", term)]);
     let support = TermProfile::detect_with_vars(vars);
@@ -191,12 +482,84 @@ fn ansi256_term(#[case] term: &str) {
 #[rstest]
 #[case("linux")]
 #[case("xterm")]
+#[case("screen")]
+#[case("screen.xterm")]
+#[case("ansi")]
+#[case("ansi.sys")]
 fn ansi16_term(#[case] term: &str) {
     let vars = make_vars(&ForceTerminal, &[("TERM", term)]);
     let support = TermProfile::detect_with_vars(vars);
     assert_eq!(TermProfile::Ansi16, support);
 }
 
+#[rstest]
+#[case("warp-ai", true, false, TermProfile::Ansi256)]
+#[case("warp-ai", false, false, TermProfile::Ansi16)]
+#[case("xterm", true, false, TermProfile::Ansi16)]
+#[case("vt100", true, true, TermProfile::Ansi16)]
+#[case("xterm-mono", true, true, TermProfile::Ansi16)]
+#[case("xterm-kitty", true, false, TermProfile::TrueColor)]
+fn optimistic_term(
+    #[case] term: &str,
+    #[case] optimistic: bool,
+    #[case] terminfo_found: bool,
+    #[case] expected: TermProfile,
+) {
+    let mut vars = make_vars(&ForceTerminal, &[("TERM", term)]);
+    vars.options.optimistic = optimistic;
+    vars.terminfo.found = terminfo_found;
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case(&[("TERM", "screen")], TermProfile::NoColor, TermProfile::NoColor)]
+#[case(&[("TERM", "screen.xterm-256color")], TermProfile::Ansi16, TermProfile::Ansi16)]
+#[case(&[("TERM", "tmux-256color"), ("TMUX", "1")], TermProfile::NoColor, TermProfile::NoColor)]
+#[case(&[("TERM", "screen.xterm-256color")], TermProfile::TrueColor, TermProfile::Ansi256)]
+#[case(&[("TERM", "xterm-256color")], TermProfile::NoColor, TermProfile::Ansi256)]
+#[case(&[("TERM", "screen"), ("FORCE_COLOR", "3")], TermProfile::NoColor, TermProfile::TrueColor)]
+fn multiplexer_cap(
+    #[case] env: &[(&str, &str)],
+    #[case] cap: TermProfile,
+    #[case] expected: TermProfile,
+) {
+    let mut vars = TermVars::from_source(
+        &HashMap::from_iter(env.iter().copied()),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .multiplexer_cap(cap),
+    );
+    vars.windows = WindowsVars::default();
+    assert_eq!(expected, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case(&[], false)]
+#[case(&[("SSH_CONNECTION", "10.0.0.2 52144 10.0.0.3 22")], true)]
+#[case(&[("SSH_CLIENT", "10.0.0.2 52144 22")], true)]
+#[case(&[("SSH_TTY", "/dev/pts/1")], true)]
+#[case(&[("SSH_TTY", "")], false)]
+fn is_ssh(#[case] env: &[(&str, &str)], #[case] expected: bool) {
+    let vars = make_vars(&ForceTerminal, env);
+    assert_eq!(expected, vars.meta.is_ssh());
+}
+
+#[test]
+fn optimistic_settings() {
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .optimistic(true),
+    );
+    assert!(vars.options.optimistic);
+}
+
 #[test]
 fn screen() {
     let vars = make_vars(
@@ -242,6 +605,63 @@ fn tmux_truecolor() {
     assert_eq!(TermProfile::TrueColor, support);
 }
 
+#[rstest]
+#[case(&[("TERM", "tmux-256color")], "Tc: (flag) true", Some(true))]
+#[case(&[("TERM", "tmux-256color")], "RGB: (flag) true", Some(true))]
+#[case(&[("TERM", "tmux-256color")], "Tc: (flag) false", Some(false))]
+#[case(&[("TERM", "tmux-256color")], "", Some(false))]
+#[case(&[("TERM", "xterm-256color")], "Tc: (flag) true", None)]
+fn tmux_truecolor_capability(
+    #[case] env: &[(&str, &str)],
+    #[case] tmux_info: &str,
+    #[case] expected: Option<bool>,
+) {
+    let mut vars = make_vars(&ForceTerminal, env);
+    vars.tmux.tmux_info = tmux_info.to_string();
+    assert_eq!(expected, vars.tmux_truecolor());
+}
+
+#[test]
+fn tmux_info_invalid_utf8() {
+    let info = TmuxVars::read_info(&b"\xff\xfe Tc: (flag) true\n"[..]).unwrap();
+    assert!(info.contains("Tc: (flag) true"));
+
+    let mut vars = make_vars(&ForceTerminal, &[("TERM", "tmux-256color")]);
+    vars.tmux.tmux_info = info;
+    assert_eq!(TermProfile::TrueColor, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case(&[("TERM", "tmux-256color")], Some(Multiplexer::Tmux))]
+#[case(&[("TMUX", "/tmp/tmux-1000/default,1,0")], Some(Multiplexer::Tmux))]
+#[case(&[("TERM", "screen")], Some(Multiplexer::Screen))]
+#[case(&[("TERM", "screen.xterm-256color")], Some(Multiplexer::Screen))]
+#[case(&[("ZELLIJ", "0")], Some(Multiplexer::Zellij))]
+#[case(&[("TERM", "xterm-256color")], None)]
+fn multiplexer(#[case] env: &[(&str, &str)], #[case] expected: Option<Multiplexer>) {
+    let vars = make_vars(&ForceTerminal, env);
+    assert_eq!(expected, vars.multiplexer());
+}
+
+#[rstest]
+#[case(&[("TERM", "xterm-kitty")], Some("kitty"))]
+#[case(&[("TERM", "screen.xterm-ghostty")], Some("ghostty"))]
+#[case(&[("TERM", "wezterm")], Some("wezterm"))]
+#[case(&[("TERM", "alacritty-direct")], Some("alacritty"))]
+#[case(&[("TERM", "putty-256color")], Some("putty"))]
+#[case(&[("VSCODE_INJECTION", "1")], Some("vscode"))]
+#[case(&[("TERM_PROGRAM", "iTerm.app"), ("TERM", "xterm-256color")], Some("iterm.app"))]
+#[case(&[("TERM", "foot-extra")], Some("foot"))]
+#[case(&[("TERM", "foot-direct")], Some("foot"))]
+#[case(&[("TERM", "st-256color")], Some("st"))]
+#[case(&[("TERM", "mlterm")], Some("mlterm"))]
+#[case(&[("TERM", "xterm-256color")], None)]
+#[case(&[("TERM", "xterm-direct")], None)]
+fn terminal_name(#[case] env: &[(&str, &str)], #[case] expected: Option<&str>) {
+    let vars = make_vars(&ForceTerminal, env);
+    assert_eq!(expected, vars.terminal_name());
+}
+
 #[test]
 fn apple_terminal() {
     let vars = make_vars(&ForceTerminal, &[("TERM_PROGRAM", "apple_terminal")]);
@@ -249,6 +669,41 @@ fn apple_terminal() {
     assert_eq!(TermProfile::Ansi256, support);
 }
 
+#[rstest]
+#[case(&[("TERM_PROGRAM", "vscode")])]
+#[case(&[("TERM_PROGRAM", "vscode"), ("TERM", "xterm-256color")])]
+#[case(&[("VSCODE_INJECTION", "1")])]
+fn vscode(#[case] env: &[(&str, &str)]) {
+    let vars = make_vars(&ForceTerminal, env);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::TrueColor, support);
+}
+
+#[test]
+fn vscode_injection_in_tmux() {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[
+            ("VSCODE_INJECTION", "1"),
+            ("TERM_PROGRAM", "tmux"),
+            ("TERM", "tmux-256color"),
+            ("TMUX", "1"),
+        ],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi256, support);
+}
+
+#[rstest]
+#[case(&[("TERM", "putty-256color")])]
+#[case(&[("TERM", "putty")])]
+#[case(&[("PUTTY", "1"), ("TERM", "xterm"), ("COLORTERM", "truecolor")])]
+fn putty(#[case] env: &[(&str, &str)]) {
+    let vars = make_vars(&ForceTerminal, env);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi256, support);
+}
+
 #[test]
 fn mintty() {
     let vars = make_vars(&ForceTerminal, &[("TERM_PROGRAM", "mintty")]);
@@ -269,6 +724,19 @@ fn iterm() {
     assert_eq!(TermProfile::TrueColor, support);
 }
 
+#[rstest]
+#[case("3.4", TermProfile::TrueColor)]
+#[case("2.0", TermProfile::Ansi256)]
+fn iterm_lc_terminal(#[case] version: &str, #[case] expected: TermProfile) {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("LC_TERMINAL", "iTerm2"), ("LC_TERMINAL_VERSION", version)],
+    );
+    assert_eq!(Some("iterm.app"), vars.terminal_name());
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
 #[test]
 fn iterm_old() {
     let vars = make_vars(
@@ -291,46 +759,169 @@ fn terminfo_truecolor() {
 }
 
 #[test]
-fn terminfo_256color() {
+fn terminfo_truecolor_untrusted() {
     let mut vars = make_vars(&ForceTerminal, &[]);
+    vars.terminfo.truecolor = Some(true);
     vars.terminfo.max_colors = Some(256);
+    vars.options.ignore_terminfo_truecolor = true;
     let support = TermProfile::detect_with_vars(vars);
     assert_eq!(TermProfile::Ansi256, support);
 }
 
 #[test]
-fn terminfo_max_colors() {
-    let mut vars = make_vars(&ForceTerminal, &[]);
-    vars.terminfo.max_colors = Some(16777216);
-    let support = TermProfile::detect_with_vars(vars);
-    assert_eq!(TermProfile::TrueColor, support);
+fn terminfo_truecolor_untrusted_settings() {
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .trust_terminfo_truecolor(false),
+    );
+    assert!(vars.options.ignore_terminfo_truecolor);
 }
 
-#[test]
-fn special_var_truecolor() {
-    let vars = make_vars(&ForceNoTerminal, &[("GOOGLE_CLOUD_SHELL", "1")]);
+#[rstest]
+#[case(Some(256), None, TermProfile::Ansi256)]
+#[case(Some(256), Some(true), TermProfile::TrueColor)]
+#[case(None, None, TermProfile::NoColor)]
+fn terminfo_override(
+    #[case] max_colors: Option<i32>,
+    #[case] truecolor: Option<bool>,
+    #[case] expected: TermProfile,
+) {
+    let mut vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .terminfo_override(max_colors, truecolor),
+    );
+    vars.windows = WindowsVars::default();
+    assert_eq!(max_colors, vars.terminfo.max_colors);
+    assert_eq!(truecolor, vars.terminfo.truecolor);
     let support = TermProfile::detect_with_vars(vars);
-    assert_eq!(TermProfile::TrueColor, support);
+    assert_eq!(expected, support);
 }
 
 #[test]
-fn special_var_ansi() {
-    let vars = make_vars(&ForceNoTerminal, &[("TRAVIS", "1")]);
+fn terminfo_256color() {
+    let mut vars = make_vars(&ForceTerminal, &[]);
+    vars.terminfo.max_colors = Some(256);
     let support = TermProfile::detect_with_vars(vars);
-    assert_eq!(TermProfile::Ansi16, support);
+    assert_eq!(TermProfile::Ansi256, support);
 }
 
 #[test]
-fn special_var_ci() {
-    let vars = make_vars(&ForceNoTerminal, &[("CI", "1")]);
+fn terminfo_88color() {
+    let mut vars = make_vars(&ForceTerminal, &[]);
+    vars.terminfo.max_colors = Some(88);
     let support = TermProfile::detect_with_vars(vars);
-    assert_eq!(TermProfile::Ansi16, support);
+    assert_eq!(TermProfile::Ansi256, support);
 }
 
-#[test]
-fn windows_con_emu() {
-    let mut vars = make_vars(&ForceTerminal, &[("ConEmuANSI", "ON")]);
-    vars.windows.is_windows = true;
+#[rstest]
+#[case(Some(8), TermProfile::Ansi16)]
+#[case(Some(256), TermProfile::Ansi256)]
+fn screen_terminfo(#[case] max_colors: Option<i32>, #[case] expected: TermProfile) {
+    let mut vars = make_vars(&ForceTerminal, &[("TERM", "screen")]);
+    vars.terminfo.max_colors = max_colors;
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case("vt220", TermProfile::Ansi8)]
+#[case("xterm", TermProfile::Ansi16)]
+#[case("xterm-new", TermProfile::Ansi16)]
+#[case("xterm-r6", TermProfile::Ansi16)]
+#[case("rxvt", TermProfile::Ansi16)]
+#[case("rxvt-unicode", TermProfile::Ansi16)]
+#[case("konsole", TermProfile::Ansi16)]
+#[case("cygwin", TermProfile::Ansi16)]
+#[case("gnome", TermProfile::Ansi16)]
+#[case("vte", TermProfile::Ansi16)]
+#[case("eterm", TermProfile::Ansi16)]
+#[case("ansi", TermProfile::Ansi16)]
+#[case("xterm-256color", TermProfile::Ansi256)]
+fn terminfo_8color(#[case] term: &str, #[case] expected: TermProfile) {
+    let mut vars = make_vars(&ForceTerminal, &[("TERM", term)]);
+    vars.terminfo.max_colors = Some(8);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case(&[("TERM", "xterm-256color")], Some(0), TermProfile::NoColor)]
+#[case(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")], Some(0), TermProfile::NoColor)]
+#[case(&[("TERM", "xterm-256color")], None, TermProfile::Ansi256)]
+#[case(&[("TERM", "xterm-256color"), ("FORCE_COLOR", "3")], Some(0), TermProfile::TrueColor)]
+fn terminfo_no_colors(
+    #[case] env: &[(&str, &str)],
+    #[case] max_colors: Option<i32>,
+    #[case] expected: TermProfile,
+) {
+    let mut vars = make_vars(&ForceTerminal, env);
+    vars.terminfo.max_colors = max_colors;
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[rstest]
+#[case("ansi")]
+#[case("ansi.sys")]
+fn ansi_sys_colorterm(#[case] term: &str) {
+    let vars = make_vars(
+        &ForceTerminal,
+        &[("TERM", term), ("COLORTERM", "truecolor")],
+    );
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[rstest]
+#[case("cons25")]
+#[case("sun-color")]
+fn term_ansi8(#[case] term: &str) {
+    let vars = make_vars(&ForceTerminal, &[("TERM", term)]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi8, support);
+}
+
+#[test]
+fn terminfo_max_colors() {
+    let mut vars = make_vars(&ForceTerminal, &[]);
+    vars.terminfo.max_colors = Some(16777216);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::TrueColor, support);
+}
+
+#[test]
+fn special_var_truecolor() {
+    let vars = make_vars(&ForceNoTerminal, &[("GOOGLE_CLOUD_SHELL", "1")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::TrueColor, support);
+}
+
+#[test]
+fn special_var_ansi() {
+    let vars = make_vars(&ForceNoTerminal, &[("TRAVIS", "1")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[test]
+fn special_var_ci() {
+    let vars = make_vars(&ForceNoTerminal, &[("CI", "1")]);
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::Ansi16, support);
+}
+
+#[test]
+fn windows_con_emu() {
+    let mut vars = make_vars(&ForceTerminal, &[("ConEmuANSI", "ON")]);
+    vars.windows.is_windows = true;
     let support = TermProfile::detect_with_vars(vars);
     assert_eq!(TermProfile::TrueColor, support);
 }
@@ -403,6 +994,31 @@ fn dumb_term_force_color() {
     assert_eq!(TermProfile::Ansi16, support);
 }
 
+#[rstest]
+#[case(false, TermProfile::NoTty)]
+#[case(true, TermProfile::NoColor)]
+fn dumb_term_as_no_color(#[case] dumb_as_no_color: bool, #[case] expected: TermProfile) {
+    let mut vars = TermVars::from_source(
+        &HashMap::from_iter([("TERM", "dumb")]),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .dumb_as_no_color(dumb_as_no_color),
+    );
+    vars.windows = WindowsVars::default();
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(expected, support);
+}
+
+#[test]
+fn dumb_term_as_no_color_no_tty() {
+    let mut vars = make_vars(&ForceNoTerminal, &[("TERM", "dumb")]);
+    vars.options.dumb_as_no_color = true;
+    let support = TermProfile::detect_with_vars(vars);
+    assert_eq!(TermProfile::NoTty, support);
+}
+
 struct FakeTerminal {
     events: VecDeque<DcsEvent>,
 }
@@ -458,6 +1074,30 @@ fn dsc_detect() {
     assert_eq!(TermProfile::TrueColor, support);
 }
 
+#[rstest]
+#[case(Rgb::from((10, 200, 30)), true)]
+#[case(Rgb::from((150, 150, 150)), false)]
+fn dsc_detect_probe_color(#[case] response: Rgb, #[case] expected: bool) {
+    let mut query_terminal = RecordingTerminal::new(FakeTerminal {
+        events: VecDeque::from_iter([
+            DcsEvent::BackgroundColor(response),
+            DcsEvent::DeviceAttributes,
+        ]),
+    });
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .probe_color(Rgb::from((10, 200, 30)))
+            .query_terminal(&mut query_terminal),
+    );
+    assert_eq!(expected, vars.meta.dcs_response);
+    let written = String::from_utf8_lossy(query_terminal.written());
+    assert!(written.contains("\x1b[48;2;10;200;30m"), "{written:?}");
+}
+
 #[test]
 fn dsc_detect_no_color() {
     let mut vars = TermVars::from_source(
@@ -483,6 +1123,653 @@ fn dsc_detect_no_color() {
     assert_eq!(TermProfile::NoColor, support);
 }
 
+#[test]
+fn background() {
+    let background = Rgb {
+        red: 10,
+        green: 20,
+        blue: 30,
+    };
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .background(background),
+    );
+    assert_eq!(Some(background), vars.meta.background);
+}
+
+#[test]
+fn dsc_detect_boxed() {
+    let query_terminal: Box<dyn QueryTerminal> = Box::new(FakeTerminal {
+        events: VecDeque::from_iter([
+            DcsEvent::BackgroundColor(Rgb {
+                red: 150,
+                green: 150,
+                blue: 150,
+            }),
+            DcsEvent::DeviceAttributes,
+        ]),
+    });
+    let settings: DetectorSettings<Box<dyn QueryTerminal>> = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(query_terminal);
+    let vars = TermVars::from_source(&HashMap::<&str, &str>::default(), &ForceTerminal, settings);
+    assert!(vars.meta.dcs_response);
+}
+
+#[test]
+fn recording_terminal() {
+    let mut query_terminal = RecordingTerminal::new(FakeTerminal {
+        events: VecDeque::from_iter([
+            DcsEvent::BackgroundColor(Rgb::from((150, 150, 150))),
+            DcsEvent::DeviceAttributes,
+        ]),
+    });
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .query_terminal(&mut query_terminal),
+    );
+    assert!(vars.meta.dcs_response);
+
+    let written = String::from_utf8_lossy(query_terminal.written());
+    assert!(written.contains("\x1b[48;2;150;150;150m"), "{written:?}");
+    assert!(written.contains("\x1bP$qm\x1b\\"), "{written:?}");
+    assert!(matches!(
+        query_terminal.events(),
+        [DcsEvent::BackgroundColor(_), DcsEvent::DeviceAttributes]
+    ));
+}
+
+#[rstest]
+#[case("MOSH_KEY")]
+#[case("MOSH_SERVER_NETWORK_TMOUT")]
+fn dsc_detect_mosh(#[case] var: &str) {
+    let mut query_terminal = RecordingTerminal::new(FakeTerminal {
+        events: VecDeque::new(),
+    });
+    let vars = TermVars::from_source(
+        &HashMap::from_iter([(var, "1"), ("TERM", "xterm-256color")]),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .query_terminal(&mut query_terminal),
+    );
+    assert!(!vars.meta.dcs_response);
+    assert!(query_terminal.written().is_empty());
+    assert!(query_terminal.events().is_empty());
+}
+
+#[test]
+fn refresh_dcs() {
+    let source = HashMap::from_iter([("TERM", "xterm-256color")]);
+    let mut vars = TermVars::from_source(
+        &source,
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false),
+    );
+    vars.windows = WindowsVars::default();
+    assert!(!vars.meta.dcs_response);
+    assert_eq!(
+        TermProfile::Ansi256,
+        TermProfile::detect_with_vars(vars.clone())
+    );
+
+    let background = Rgb {
+        red: 10,
+        green: 20,
+        blue: 30,
+    };
+    vars.refresh_dcs(
+        &source,
+        &ForceTerminal,
+        DetectorSettings::new()
+            .background(background)
+            .query_terminal(FakeTerminal {
+                events: VecDeque::from_iter([
+                    DcsEvent::BackgroundColor(Rgb {
+                        red: 150,
+                        green: 150,
+                        blue: 150,
+                    }),
+                    DcsEvent::DeviceAttributes,
+                ]),
+            }),
+    )
+    .unwrap();
+    assert!(vars.meta.dcs_response);
+    assert_eq!(Some(background), vars.meta.background);
+    assert_eq!(TermProfile::TrueColor, TermProfile::detect_with_vars(vars));
+}
+
+// Escape sequence events as they might be parsed by a TUI framework's own input reader
+enum TuiEvent {
+    Key(char),
+    Resize(u16, u16),
+    SgrResponse(u8, u8, u8),
+    PrimaryDeviceAttributes,
+}
+
+// Query terminal that reuses an existing TUI event stream instead of opening the terminal again
+struct TuiQueryTerminal {
+    events: mpsc::Receiver<TuiEvent>,
+    written: Vec<u8>,
+    skipped: Vec<TuiEvent>,
+}
+
+impl QueryTerminal for TuiQueryTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        // The TUI has already enabled raw mode
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        let Ok(event) = self.events.recv_timeout(Duration::from_millis(100)) else {
+            return Ok(DcsEvent::TimedOut);
+        };
+        Ok(match event {
+            TuiEvent::SgrResponse(red, green, blue) => {
+                DcsEvent::BackgroundColor(Rgb { red, green, blue })
+            }
+            TuiEvent::PrimaryDeviceAttributes => DcsEvent::DeviceAttributes,
+            event => {
+                // Hand unrelated events back to the application
+                self.skipped.push(event);
+                DcsEvent::Other
+            }
+        })
+    }
+}
+
+impl io::Write for TuiQueryTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn dsc_detect_tui_event_source() {
+    let (tx, rx) = mpsc::channel();
+    let input = thread::spawn(move || {
+        for event in [
+            TuiEvent::Key('a'),
+            TuiEvent::Resize(80, 24),
+            TuiEvent::SgrResponse(150, 150, 150),
+            TuiEvent::PrimaryDeviceAttributes,
+        ] {
+            tx.send(event).unwrap();
+        }
+    });
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(TuiQueryTerminal {
+            events: rx,
+            written: Vec::new(),
+            skipped: Vec::new(),
+        });
+    let meta = TermMetaVars::from_source(
+        &HashMap::from_iter([("TERM", "xterm")]),
+        &ForceTerminal,
+        &mut settings,
+    );
+    input.join().unwrap();
+
+    assert!(meta.dcs_response);
+    let query_terminal = settings.query_terminal;
+    assert!(!query_terminal.written.is_empty());
+    assert!(matches!(
+        query_terminal.skipped.as_slice(),
+        [TuiEvent::Key('a'), TuiEvent::Resize(80, 24)]
+    ));
+}
+
+#[rstest]
+#[case(DcsEvent::BackgroundColor(Rgb::from((10, 20, 30))), true)]
+#[case(DcsEvent::BackgroundColor(Rgb::from((0, 0, 0))), false)]
+#[case(DcsEvent::Other, false)]
+fn verify_color(#[case] response: DcsEvent, #[case] expected: bool) {
+    let mut query_terminal = FakeTerminal {
+        events: VecDeque::from_iter([response, DcsEvent::DeviceAttributes]),
+    };
+    assert_eq!(
+        expected,
+        crate::verify_color(&mut query_terminal, Rgb::from((10, 20, 30))).unwrap()
+    );
+}
+
+#[test]
+fn verify_color_timeout() {
+    let mut query_terminal = FakeTerminal {
+        events: VecDeque::from_iter([
+            DcsEvent::BackgroundColor(Rgb::from((10, 20, 30))),
+            DcsEvent::TimedOut,
+        ]),
+    };
+    assert!(!crate::verify_color(&mut query_terminal, Rgb::from((10, 20, 30))).unwrap());
+}
+
+#[test]
+fn query_palette() {
+    let mut query_terminal = FakeTerminal {
+        events: VecDeque::from_iter([
+            DcsEvent::PaletteColor {
+                index: 1,
+                rgb: Rgb::from((205, 49, 49)),
+            },
+            DcsEvent::Other,
+            // Not requested
+            DcsEvent::PaletteColor {
+                index: 3,
+                rgb: Rgb::from((229, 229, 16)),
+            },
+            DcsEvent::PaletteColor {
+                index: 2,
+                rgb: Rgb::from((13, 188, 121)),
+            },
+            DcsEvent::DeviceAttributes,
+        ]),
+    };
+    let colors =
+        crate::query_palette(&mut query_terminal, &[1, 2, 4], Duration::from_secs(1)).unwrap();
+    assert_eq!(
+        vec![
+            (1, Rgb::from((205, 49, 49))),
+            (2, Rgb::from((13, 188, 121)))
+        ],
+        colors
+    );
+}
+
+#[test]
+fn query_or_fallback() {
+    let settings = DetectorSettings::query_or_fallback(Err::<FakeTerminal, _>(io::Error::other(
+        "no controlling terminal",
+    )));
+    assert!(!settings.enable_query);
+
+    let settings = DetectorSettings::query_or_fallback(Ok(FakeTerminal {
+        events: VecDeque::new(),
+    }));
+    assert!(settings.enable_query);
+}
+
+#[test]
+fn thorough() {
+    let settings = DetectorSettings::thorough_with(Ok(FakeTerminal {
+        events: VecDeque::new(),
+    }))
+    .unwrap();
+    assert!(settings.enable_query);
+    assert!(settings.enable_terminfo);
+    assert!(settings.enable_tmux_info);
+    assert!(!settings.options.ignore_terminfo_truecolor);
+    assert!(settings.options.optimistic);
+
+    let settings = DetectorSettings::thorough_with(Err::<FakeTerminal, _>(io::Error::other(
+        "no controlling terminal",
+    )));
+    assert!(settings.is_err());
+}
+
+struct SecondaryAttributesTerminal {
+    events: VecDeque<DcsEvent>,
+    written: Vec<u8>,
+}
+
+impl QueryTerminal for SecondaryAttributesTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        Ok(self.events.pop_front().unwrap())
+    }
+
+    fn query_secondary_attributes(&self) -> bool {
+        true
+    }
+}
+
+impl io::Write for SecondaryAttributesTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+#[case(41, 331, true)]
+#[case(41, 390, true)]
+#[case(41, 330, false)]
+#[case(1, 4000, false)]
+fn dsc_detect_secondary_attributes(
+    #[case] kind: u16,
+    #[case] version: u32,
+    #[case] expected: bool,
+) {
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(SecondaryAttributesTerminal {
+            events: VecDeque::from_iter([
+                // Terminal doesn't support the background round trip
+                DcsEvent::Other,
+                DcsEvent::SecondaryAttributes { kind, version },
+                DcsEvent::DeviceAttributes,
+            ]),
+            written: Vec::new(),
+        });
+    let meta = TermMetaVars::from_source(
+        &HashMap::from_iter([("TERM", "xterm")]),
+        &ForceTerminal,
+        &mut settings,
+    );
+    assert_eq!(expected, meta.dcs_response);
+    let written = String::from_utf8(settings.query_terminal.written).unwrap();
+    assert!(written.ends_with("\x1b[>c\x1b[c"));
+}
+
+#[test]
+fn dcs_detect_secondary_attributes_background_mismatch() {
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(SecondaryAttributesTerminal {
+            events: VecDeque::from_iter([
+                // The background round trip failed, so DA2 shouldn't override it
+                DcsEvent::BackgroundColor(Rgb::from((0, 0, 0))),
+                DcsEvent::SecondaryAttributes {
+                    kind: 41,
+                    version: 390,
+                },
+                DcsEvent::DeviceAttributes,
+            ]),
+            written: Vec::new(),
+        });
+    let meta = TermMetaVars::from_source(
+        &HashMap::from_iter([("TERM", "xterm")]),
+        &ForceTerminal,
+        &mut settings,
+    );
+    assert!(!meta.dcs_response);
+}
+
+struct KittyGraphicsTerminal {
+    events: VecDeque<DcsEvent>,
+    written: Vec<u8>,
+}
+
+impl QueryTerminal for KittyGraphicsTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        Ok(self.events.pop_front().unwrap())
+    }
+
+    fn query_kitty_graphics(&self) -> bool {
+        true
+    }
+}
+
+impl io::Write for KittyGraphicsTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+#[case(vec![DcsEvent::KittyGraphics(true), DcsEvent::DeviceAttributes], true)]
+#[case(vec![DcsEvent::KittyGraphics(false), DcsEvent::DeviceAttributes], false)]
+#[case(vec![DcsEvent::DeviceAttributes], false)]
+fn dsc_detect_kitty_graphics(#[case] events: Vec<DcsEvent>, #[case] expected: bool) {
+    let mut events = VecDeque::from(events);
+    events.push_front(DcsEvent::BackgroundColor(Rgb::from((150, 150, 150))));
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(KittyGraphicsTerminal {
+            events,
+            written: Vec::new(),
+        });
+    let meta = TermMetaVars::from_source(
+        &HashMap::from_iter([("TERM", "xterm-kitty")]),
+        &ForceTerminal,
+        &mut settings,
+    );
+    assert!(meta.dcs_response);
+    assert_eq!(expected, meta.kitty_graphics);
+    let written = String::from_utf8(settings.query_terminal.written).unwrap();
+    assert!(
+        written.ends_with("a=q,t=d,f=24;AAAA\x1b\\\x1b[c"),
+        "{written:?}"
+    );
+}
+
+#[rstest]
+#[case(DcsEvent::KittyGraphics(true), true)]
+#[case(DcsEvent::KittyGraphics(false), false)]
+#[case(DcsEvent::Other, false)]
+fn query_kitty_graphics(#[case] response: DcsEvent, #[case] expected: bool) {
+    let mut query_terminal = FakeTerminal {
+        events: VecDeque::from_iter([response, DcsEvent::DeviceAttributes]),
+    };
+    assert_eq!(
+        expected,
+        crate::query_kitty_graphics(&mut query_terminal, Duration::from_secs(1)).unwrap()
+    );
+}
+
+#[test]
+fn query_timeout() {
+    let start = Instant::now();
+    assert!(!crate::query_kitty_graphics(&mut SlowTerminal, Duration::from_millis(50)).unwrap());
+    assert!(
+        crate::query_palette(&mut SlowTerminal, &[1], Duration::from_millis(50))
+            .unwrap()
+            .is_empty()
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+struct FailingTerminal {
+    raw_mode: bool,
+}
+
+impl QueryTerminal for FailingTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        self.raw_mode = true;
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.raw_mode = false;
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        Err(io::Error::other("read failed"))
+    }
+}
+
+impl io::Write for FailingTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn query_cleanup_on_error() {
+    let mut query_terminal = FailingTerminal { raw_mode: false };
+    assert!(crate::query_kitty_graphics(&mut query_terminal, Duration::from_secs(1)).is_err());
+    assert!(!query_terminal.raw_mode);
+
+    assert!(crate::query_palette(&mut query_terminal, &[1], Duration::from_secs(1)).is_err());
+    assert!(!query_terminal.raw_mode);
+
+    assert!(crate::verify_color(&mut query_terminal, Rgb::from((10, 20, 30))).is_err());
+    assert!(!query_terminal.raw_mode);
+
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(FailingTerminal { raw_mode: false });
+    let meta = TermMetaVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        &mut settings,
+    );
+    assert!(!meta.dcs_response);
+    assert!(!settings.query_terminal.raw_mode);
+}
+
+struct SlowTerminal;
+
+impl QueryTerminal for SlowTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        thread::sleep(Duration::from_millis(10));
+        Ok(DcsEvent::Other)
+    }
+}
+
+impl io::Write for SlowTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Sends a number of unrelated events before responding to the query
+struct ChattyTerminal {
+    others: usize,
+    sent_background: bool,
+}
+
+impl QueryTerminal for ChattyTerminal {
+    fn setup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        if self.others > 0 {
+            self.others -= 1;
+            thread::sleep(Duration::from_millis(1));
+            Ok(DcsEvent::Other)
+        } else if !self.sent_background {
+            self.sent_background = true;
+            Ok(DcsEvent::BackgroundColor(Rgb {
+                red: 150,
+                green: 150,
+                blue: 150,
+            }))
+        } else {
+            Ok(DcsEvent::DeviceAttributes)
+        }
+    }
+}
+
+impl io::Write for ChattyTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+#[case(10, None, true)]
+#[case(10, Some(Duration::from_secs(5)), true)]
+#[case(5000, Some(Duration::from_millis(20)), false)]
+fn query_deadline(#[case] others: usize, #[case] budget: Option<Duration>, #[case] expected: bool) {
+    let start = Instant::now();
+    let mut settings = DetectorSettings::new()
+        .enable_terminfo(false)
+        .enable_tmux_info(false)
+        .query_terminal(ChattyTerminal {
+            others,
+            sent_background: false,
+        });
+    settings.total_budget = budget;
+    let vars = TermVars::from_source(&HashMap::<&str, &str>::default(), &ForceTerminal, settings);
+    assert_eq!(expected, vars.meta.dcs_response);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn total_budget() {
+    let start = Instant::now();
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+            .total_budget(Duration::from_millis(50))
+            .query_terminal(SlowTerminal),
+    );
+    assert!(!vars.meta.dcs_response);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
 fn make_vars<T>(out: &T, vars: &[(&str, &str)]) -> TermVars
 where
     T: IsTerminal,
@@ -518,3 +1805,249 @@ impl IsTerminal for ForceNoTerminal {
         false
     }
 }
+
+#[test]
+fn rgb_from_tuple() {
+    assert_eq!(
+        Rgb {
+            red: 1,
+            green: 2,
+            blue: 3
+        },
+        Rgb::from((1, 2, 3))
+    );
+}
+
+#[rstest]
+#[case("#ffffff", Ok(Rgb::from((255, 255, 255))))]
+#[case("#000", Ok(Rgb::from((0, 0, 0))))]
+#[case("#1a2B3c", Ok(Rgb::from((0x1a, 0x2b, 0x3c))))]
+#[case("#f80", Ok(Rgb::from((0xff, 0x88, 0x00))))]
+#[case("102030", Ok(Rgb::from((0x10, 0x20, 0x30))))]
+#[case("#ggg", Err(ParseRgbError))]
+#[case("#12345", Err(ParseRgbError))]
+#[case("#+12345", Err(ParseRgbError))]
+#[case("#ääa", Err(ParseRgbError))]
+#[case("", Err(ParseRgbError))]
+fn rgb_from_hex(#[case] hex: &str, #[case] expected: Result<Rgb, ParseRgbError>) {
+    assert_eq!(expected, Rgb::from_hex(hex));
+}
+
+#[cfg(unix)]
+#[test]
+fn fd_not_terminal() {
+    use std::os::fd::AsFd;
+
+    let file = std::fs::File::open("Cargo.toml").unwrap();
+    assert!(!IsTerminal::is_terminal(&file.as_fd()));
+    let vars = TermVars::from_source(
+        &HashMap::<&str, &str>::default(),
+        &file.as_fd(),
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false),
+    );
+    assert_eq!(TermProfile::NoTty, TermProfile::detect_with_vars(vars));
+}
+
+#[rstest]
+#[case(&[], None)]
+#[case(&[("NO_COLOR", "1")], Some(TermProfile::NoColor))]
+#[case(&[("NO_COLOR", "1"), ("FORCE_COLOR", "3")], Some(TermProfile::NoColor))]
+#[case(&[("CLICOLOR", "never"), ("COLOR_PROFILE", "truecolor")], Some(TermProfile::NoColor))]
+#[case(&[("NO_COLOR", "0")], None)]
+#[case(&[("COLOR_PROFILE", "ansi256")], Some(TermProfile::Ansi256))]
+#[case(&[("COLOR_PROFILE", "notty")], Some(TermProfile::NoTty))]
+#[case(&[("COLOR_PROFILE", "truecolor"), ("FORCE_COLOR", "ansi16")], Some(TermProfile::TrueColor))]
+#[case(&[("FORCE_COLOR", "1")], None)]
+#[case(&[("FORCE_COLOR", "always")], None)]
+#[case(&[("FORCE_COLOR", "never")], Some(TermProfile::NoColor))]
+#[case(&[("FORCE_COLOR", "ansi16")], Some(TermProfile::Ansi16))]
+#[case(&[("FORCE_COLOR", "2")], Some(TermProfile::Ansi256))]
+#[case(&[("FORCE_COLOR", "truecolor")], Some(TermProfile::TrueColor))]
+#[case(&[("CLICOLOR_FORCE", "3")], Some(TermProfile::TrueColor))]
+#[case(&[("CLICOLOR_FORCE", "ansi256"), ("FORCE_COLOR", "3")], Some(TermProfile::Ansi256))]
+#[case(&[("TERM", "xterm-256color")], None)]
+fn from_overrides(#[case] vars: &[(&str, &str)], #[case] expected: Option<TermProfile>) {
+    let source: HashMap<_, _> = vars.iter().copied().collect();
+    assert_eq!(expected, TermProfile::from_overrides(&source));
+}
+
+#[cfg(feature = "terminfo")]
+#[test]
+fn from_terminfo_fixture() {
+    let info = termini::TermInfo::from_path(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/terminfo/f/fixture-256"
+    ))
+    .unwrap();
+    let terminfo = super::TerminfoVars::from_terminfo(&info);
+    assert_eq!(Some(256), terminfo.max_colors);
+    assert_eq!(
+        TermProfile::Ansi256,
+        TermProfile::from_term_and_terminfo("fixture-256", terminfo)
+    );
+}
+
+#[cfg(feature = "terminfo")]
+#[rstest]
+#[case("xterm-256color", TermProfile::Ansi256)]
+#[case("alacritty", TermProfile::TrueColor)]
+#[case("dumb", TermProfile::NoTty)]
+fn from_terminfo_name(#[case] term: &str, #[case] expected: TermProfile) {
+    assert_eq!(expected, TermProfile::from_terminfo_name(term));
+}
+
+#[cfg(feature = "color-cache")]
+struct CountingSource {
+    vars: HashMap<String, String>,
+    lookups: std::cell::Cell<usize>,
+}
+
+#[cfg(feature = "color-cache")]
+impl crate::EnvVarSource for CountingSource {
+    fn var(&self, key: &str) -> Option<String> {
+        self.lookups.set(self.lookups.get() + 1);
+        self.vars.get(key).cloned()
+    }
+}
+
+#[cfg(feature = "color-cache")]
+#[test]
+fn detect_cached_by_env() {
+    let source = CountingSource {
+        vars: HashMap::from_iter([
+            ("TERM".to_string(), "xterm-256color".to_string()),
+            // Unique value so other tests don't share the cache entry
+            (
+                "TERM_PROGRAM_VERSION".to_string(),
+                "detect_cached_by_env".to_string(),
+            ),
+        ]),
+        lookups: std::cell::Cell::default(),
+    };
+    let profile =
+        TermProfile::detect_cached_by_env(&source, &ForceTerminal, DetectorSettings::new());
+    let first_lookups = source.lookups.replace(0);
+
+    let cached =
+        TermProfile::detect_cached_by_env(&source, &ForceTerminal, DetectorSettings::new());
+    assert_eq!(profile, cached);
+    // Only the variables needed to compute the cache key are read
+    assert!(source.lookups.get() < first_lookups);
+
+    let no_terminal =
+        TermProfile::detect_cached_by_env(&source, &ForceNoTerminal, DetectorSettings::new());
+    assert_eq!(TermProfile::NoTty, no_terminal);
+}
+
+#[cfg(feature = "color-cache")]
+#[test]
+fn detect_cached_by_env_settings() {
+    let source = HashMap::from_iter([
+        ("TERM", "dumb"),
+        // Unique value so other tests don't share the cache entry
+        ("TERM_PROGRAM_VERSION", "detect_cached_by_env_settings"),
+    ]);
+    let settings = || {
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+    };
+    assert_eq!(
+        TermProfile::NoTty,
+        TermProfile::detect_cached_by_env(&source, &ForceTerminal, settings())
+    );
+    // Different options shouldn't reuse the previous result
+    assert_eq!(
+        TermProfile::NoColor,
+        TermProfile::detect_cached_by_env(
+            &source,
+            &ForceTerminal,
+            settings().dumb_as_no_color(true)
+        )
+    );
+    assert_eq!(
+        TermProfile::NoTty,
+        TermProfile::detect_cached_by_env(&source, &ForceTerminal, settings())
+    );
+}
+
+struct KeyRecorder {
+    keys: std::cell::RefCell<Vec<String>>,
+}
+
+impl crate::EnvVarSource for KeyRecorder {
+    fn var(&self, key: &str) -> Option<String> {
+        self.keys.borrow_mut().push(key.to_string());
+        None
+    }
+}
+
+#[test]
+fn relevant_env_vars() {
+    for var in ["NO_COLOR", "FORCE_COLOR", "TERM"] {
+        assert!(crate::RELEVANT_ENV_VARS.contains(&var));
+    }
+    let mut unique = crate::RELEVANT_ENV_VARS.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(crate::RELEVANT_ENV_VARS.len(), unique.len());
+
+    let recorder = KeyRecorder {
+        keys: std::cell::RefCell::default(),
+    };
+    TermVars::from_source(
+        &recorder,
+        &ForceTerminal,
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false),
+    );
+    for key in recorder.keys.into_inner() {
+        assert!(
+            crate::RELEVANT_ENV_VARS.contains(&key.as_str()),
+            "{key} is missing"
+        );
+    }
+}
+
+#[test]
+fn debug_report() {
+    let mut vars = make_vars(
+        &ForceTerminal,
+        &[
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor"),
+            ("TMUX", "/tmp/tmux-1000/default,1,0"),
+        ],
+    );
+    vars.terminfo.max_colors = Some(256);
+    let report = vars.debug_report();
+
+    assert!(report.starts_with("profile: Ansi256\n"));
+    assert!(report.contains("\n[overrides]\n  NO_COLOR: <unset>\n"));
+    assert!(report.contains("\n  TERM: \"xterm-256color\"\n"));
+    assert!(report.contains("\n  COLORTERM: \"truecolor\"\n"));
+    assert!(report.contains("\n  is_terminal: true\n"));
+    assert!(report.contains("\n[tmux]\n  TMUX: \"/tmp/tmux-1000/default,1,0\"\n"));
+    assert!(report.contains("\n  max_colors: Some(256)\n"));
+    assert_eq!(report, vars.debug_report());
+}
+
+#[test]
+fn detect_std_handles() {
+    let settings = || {
+        DetectorSettings::new()
+            .enable_terminfo(false)
+            .enable_tmux_info(false)
+    };
+    assert_eq!(
+        TermProfile::detect(&io::stdout(), settings()),
+        TermProfile::detect_stdout(settings())
+    );
+    assert_eq!(
+        TermProfile::detect(&io::stderr(), settings()),
+        TermProfile::detect_stderr(settings())
+    );
+}
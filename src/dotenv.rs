@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+
+use crate::EnvVarSource;
+
+/// Source that reads environment variables from text in the dotenv format.
+///
+/// This is useful for reproducing the environment from another system. Each line should contain
+/// a `KEY=value` pair. Blank lines and lines starting with `#` are ignored. Values may be
+/// wrapped in single or double quotes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotEnv {
+    vars: HashMap<String, String>,
+}
+
+impl DotEnv {
+    /// Parses the variables from the given text. Lines that can't be parsed are skipped.
+    pub fn parse(text: &str) -> Self {
+        let vars = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let line = line.strip_prefix("export ").unwrap_or(line);
+                let (key, value) = line.split_once('=')?;
+                let key = key.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                Some((key.to_string(), parse_value(value.trim())))
+            })
+            .collect();
+        Self { vars }
+    }
+
+    /// Reads and parses the variables from the file at the given path.
+    pub fn from_file<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+}
+
+fn parse_value(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.rsplit_once(quote))
+            .map(|(v, _)| v)
+        {
+            return if quote == '"' {
+                inner.replace("\\n", "\n").replace("\\\"", "\"")
+            } else {
+                inner.to_string()
+            };
+        }
+    }
+    // Unquoted values may have a trailing comment
+    value
+        .split_once(" #")
+        .map(|(v, _)| v)
+        .unwrap_or(value)
+        .trim_end()
+        .to_string()
+}
+
+impl EnvVarSource for DotEnv {
+    fn var(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+}
+
+#[cfg(test)]
+#[path = "./dotenv_test.rs"]
+mod dotenv_test;
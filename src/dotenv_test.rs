@@ -0,0 +1,37 @@
+use super::DotEnv;
+use crate::EnvVarSource;
+
+#[test]
+fn parse_basic() {
+    let env = DotEnv::parse("TERM=xterm-256color\nCOLORTERM=truecolor");
+    assert_eq!(env.var("TERM").as_deref(), Some("xterm-256color"));
+    assert_eq!(env.var("COLORTERM").as_deref(), Some("truecolor"));
+    assert_eq!(env.var("NO_COLOR"), None);
+}
+
+#[test]
+fn parse_quoted() {
+    let env = DotEnv::parse(
+        r#"
+        DOUBLE="xterm # not a comment"
+        SINGLE='wezterm'
+        ESCAPED="say \"hi\""
+        EMPTY=""
+        "#,
+    );
+    assert_eq!(env.var("DOUBLE").as_deref(), Some("xterm # not a comment"));
+    assert_eq!(env.var("SINGLE").as_deref(), Some("wezterm"));
+    assert_eq!(env.var("ESCAPED").as_deref(), Some("say \"hi\""));
+    assert_eq!(env.var("EMPTY").as_deref(), Some(""));
+}
+
+#[test]
+fn parse_comments() {
+    let env = DotEnv::parse(
+        "# comment\n\n  # indented comment\nTERM=kitty # trailing comment\nexport \
+         NO_COLOR=1\n=x\ninvalid",
+    );
+    assert_eq!(env.var("TERM").as_deref(), Some("kitty"));
+    assert_eq!(env.var("NO_COLOR").as_deref(), Some("1"));
+    assert_eq!(env, DotEnv::parse("TERM=kitty\nNO_COLOR=1"));
+}
@@ -3,28 +3,36 @@
 #![forbid(clippy::unwrap_used)]
 #![doc = include_str!("../README.md")]
 
-#[cfg(feature = "convert")]
+#[cfg(feature = "anstream")]
+mod color_choice;
+#[cfg(any(feature = "convert-anstyle", feature = "convert-ratatui"))]
 mod convert;
 mod detect;
+mod dotenv;
 #[cfg(feature = "query-detect")]
 mod query;
 
-#[cfg(feature = "convert")]
+#[cfg(feature = "anstream")]
+pub use anstream;
+#[cfg(any(feature = "convert-anstyle", feature = "convert-ratatui"))]
 pub use anstyle;
-#[cfg(feature = "convert")]
+#[cfg(any(feature = "convert-anstyle", feature = "convert-ratatui"))]
 pub use convert::*;
 pub use detect::*;
+pub use dotenv::*;
 #[cfg(feature = "query-detect")]
 pub use query::*;
 
 /// Terminal color profile.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TermProfile {
     /// No terminal is attached. This may happen if the output is piped or if the program was not
     /// ran from a TTY.
     NoTty,
     /// Text modifiers may be used, but no colors should be emitted.
     NoColor,
+    /// Only the 8 base colors are supported. Bright colors are not available.
+    Ansi8,
     /// 16 colors are supported.
     Ansi16,
     /// 256 colors are supported.
@@ -32,3 +40,116 @@ pub enum TermProfile {
     /// Any RGB color is supported.
     TrueColor,
 }
+
+/// Value of a command line color flag, such as `--color=auto|always|never`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFlag {
+    /// Use the detected profile.
+    #[default]
+    Auto,
+    /// Always emit colors, using at least the given profile. If no profile is given,
+    /// [`Ansi16`](TermProfile::Ansi16) is used.
+    Always(Option<TermProfile>),
+    /// Never emit colors.
+    Never,
+}
+
+impl TermProfile {
+    /// Returns the effective profile after applying the user's color flag to the detected profile.
+    ///
+    /// - [`Auto`](ColorFlag::Auto) returns the detected profile unchanged.
+    /// - [`Always`](ColorFlag::Always) returns the detected profile if it's higher than the
+    ///   requested level, otherwise the requested level.
+    /// - [`Never`](ColorFlag::Never) returns [`NoColor`](Self::NoColor), unless the detected
+    ///   profile is [`NoTty`](Self::NoTty), which is kept so no escape sequences are emitted.
+    ///
+    /// ```
+    /// use termprofile::{ColorFlag, TermProfile};
+    ///
+    /// assert_eq!(
+    ///     TermProfile::Ansi16,
+    ///     TermProfile::resolve_cli(ColorFlag::Always(None), TermProfile::NoTty)
+    /// );
+    /// assert_eq!(
+    ///     TermProfile::TrueColor,
+    ///     TermProfile::resolve_cli(ColorFlag::Always(None), TermProfile::TrueColor)
+    /// );
+    /// ```
+    pub fn resolve_cli(flag: ColorFlag, detected: Self) -> Self {
+        match flag {
+            ColorFlag::Auto => detected,
+            ColorFlag::Always(level) => detected.max(level.unwrap_or(Self::Ansi16)),
+            ColorFlag::Never => detected.min(Self::NoColor),
+        }
+    }
+
+    /// Returns `true` if output requested at the given profile will be degraded when written
+    /// using this profile, such as when true color is requested but only 256 colors are supported.
+    /// [`NoTty`](Self::NoTty) degrades any request other than [`NoTty`](Self::NoTty) itself.
+    pub fn will_degrade(&self, requested: Self) -> bool {
+        *self < requested
+    }
+
+    /// Returns a one line human-readable description of the profile, suitable for diagnostic
+    /// output such as `--version`.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::NoTty => "No TTY detected (output is not a terminal)",
+            Self::NoColor => "No color support (text styles only) on a TTY",
+            Self::Ansi8 => "ANSI 8 colors on a TTY",
+            Self::Ansi16 => "ANSI 16 colors on a TTY",
+            Self::Ansi256 => "ANSI 256 colors on a TTY",
+            Self::TrueColor => "True color (16.7M colors) on a TTY",
+        }
+    }
+
+    /// Returns the least capable profile from the given profiles, such as when choosing a profile
+    /// that's safe to use for several outputs at once. Since [`NoTty`](Self::NoTty) is the least
+    /// capable profile, it takes precedence over every other profile.
+    ///
+    /// Returns [`None`] if the iterator is empty.
+    ///
+    /// ```
+    /// use termprofile::TermProfile;
+    ///
+    /// assert_eq!(
+    ///     Some(TermProfile::NoTty),
+    ///     TermProfile::min_of([TermProfile::TrueColor, TermProfile::NoTty])
+    /// );
+    /// ```
+    pub fn min_of<I>(profiles: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        profiles.into_iter().min()
+    }
+
+    /// Returns the most capable profile from the given profiles.
+    ///
+    /// Returns [`None`] if the iterator is empty.
+    ///
+    /// ```
+    /// use termprofile::TermProfile;
+    ///
+    /// assert_eq!(
+    ///     Some(TermProfile::TrueColor),
+    ///     TermProfile::max_of([TermProfile::TrueColor, TermProfile::NoTty])
+    /// );
+    /// ```
+    pub fn max_of<I>(profiles: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        profiles.into_iter().max()
+    }
+
+    /// Returns the escape sequence for resetting all styles. This is empty for
+    /// [`NoTty`](Self::NoTty) since no escape sequences should be emitted.
+    pub fn reset_escape(&self) -> &'static str {
+        if *self == Self::NoTty { "" } else { "\x1b[0m" }
+    }
+}
+
+#[cfg(test)]
+#[path = "./lib_test.rs"]
+mod lib_test;
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use rstest::rstest;
+
+use crate::{ColorFlag, TermProfile};
+
+#[rstest]
+#[case(TermProfile::NoTty, "")]
+#[case(TermProfile::NoColor, "\x1b[0m")]
+#[case(TermProfile::Ansi8, "\x1b[0m")]
+#[case(TermProfile::Ansi16, "\x1b[0m")]
+#[case(TermProfile::Ansi256, "\x1b[0m")]
+#[case(TermProfile::TrueColor, "\x1b[0m")]
+fn reset_escape(#[case] profile: TermProfile, #[case] expected: &str) {
+    assert_eq!(expected, profile.reset_escape());
+}
+
+#[test]
+fn ansi8_ordering() {
+    assert!(TermProfile::NoColor < TermProfile::Ansi8);
+    assert!(TermProfile::Ansi8 < TermProfile::Ansi16);
+}
+
+#[rstest]
+#[case(ColorFlag::Auto, TermProfile::NoTty, TermProfile::NoTty)]
+#[case(ColorFlag::Auto, TermProfile::NoColor, TermProfile::NoColor)]
+#[case(ColorFlag::Auto, TermProfile::Ansi256, TermProfile::Ansi256)]
+#[case(ColorFlag::Always(None), TermProfile::NoTty, TermProfile::Ansi16)]
+#[case(ColorFlag::Always(None), TermProfile::NoColor, TermProfile::Ansi16)]
+#[case(ColorFlag::Always(None), TermProfile::Ansi8, TermProfile::Ansi16)]
+#[case(
+    ColorFlag::Always(None),
+    TermProfile::TrueColor,
+    TermProfile::TrueColor
+)]
+#[case(
+    ColorFlag::Always(Some(TermProfile::Ansi256)),
+    TermProfile::NoTty,
+    TermProfile::Ansi256
+)]
+#[case(
+    ColorFlag::Always(Some(TermProfile::Ansi256)),
+    TermProfile::Ansi16,
+    TermProfile::Ansi256
+)]
+#[case(
+    ColorFlag::Always(Some(TermProfile::Ansi8)),
+    TermProfile::TrueColor,
+    TermProfile::TrueColor
+)]
+#[case(ColorFlag::Never, TermProfile::NoTty, TermProfile::NoTty)]
+#[case(ColorFlag::Never, TermProfile::NoColor, TermProfile::NoColor)]
+#[case(ColorFlag::Never, TermProfile::Ansi16, TermProfile::NoColor)]
+#[case(ColorFlag::Never, TermProfile::TrueColor, TermProfile::NoColor)]
+fn resolve_cli(
+    #[case] flag: ColorFlag,
+    #[case] detected: TermProfile,
+    #[case] expected: TermProfile,
+) {
+    assert_eq!(expected, TermProfile::resolve_cli(flag, detected));
+}
+
+#[rstest]
+#[case(TermProfile::TrueColor, TermProfile::TrueColor, false)]
+#[case(TermProfile::TrueColor, TermProfile::Ansi16, false)]
+#[case(TermProfile::Ansi256, TermProfile::TrueColor, true)]
+#[case(TermProfile::Ansi256, TermProfile::Ansi256, false)]
+#[case(TermProfile::Ansi16, TermProfile::Ansi256, true)]
+#[case(TermProfile::Ansi8, TermProfile::Ansi16, true)]
+#[case(TermProfile::NoColor, TermProfile::Ansi8, true)]
+#[case(TermProfile::NoColor, TermProfile::NoTty, false)]
+#[case(TermProfile::NoTty, TermProfile::NoTty, false)]
+fn will_degrade(
+    #[case] profile: TermProfile,
+    #[case] requested: TermProfile,
+    #[case] expected: bool,
+) {
+    assert_eq!(expected, profile.will_degrade(requested));
+}
+
+#[rstest]
+fn no_tty_degrades(
+    #[values(
+        TermProfile::NoColor,
+        TermProfile::Ansi8,
+        TermProfile::Ansi16,
+        TermProfile::Ansi256,
+        TermProfile::TrueColor
+    )]
+    requested: TermProfile,
+) {
+    assert!(TermProfile::NoTty.will_degrade(requested));
+}
+
+#[test]
+fn describe() {
+    let profiles = [
+        TermProfile::NoTty,
+        TermProfile::NoColor,
+        TermProfile::Ansi8,
+        TermProfile::Ansi16,
+        TermProfile::Ansi256,
+        TermProfile::TrueColor,
+    ];
+    let descriptions: HashSet<_> = profiles.iter().map(TermProfile::describe).collect();
+    assert_eq!(profiles.len(), descriptions.len());
+    assert!(descriptions.iter().all(|d| !d.is_empty()));
+}
+
+#[rstest]
+#[case(&[TermProfile::TrueColor, TermProfile::NoTty], Some(TermProfile::NoTty), Some(TermProfile::TrueColor))]
+#[case(&[TermProfile::Ansi256, TermProfile::Ansi16, TermProfile::TrueColor], Some(TermProfile::Ansi16), Some(TermProfile::TrueColor))]
+#[case(&[TermProfile::NoColor], Some(TermProfile::NoColor), Some(TermProfile::NoColor))]
+#[case(&[], None, None)]
+fn min_max_of(
+    #[case] profiles: &[TermProfile],
+    #[case] min: Option<TermProfile>,
+    #[case] max: Option<TermProfile>,
+) {
+    assert_eq!(min, TermProfile::min_of(profiles.iter().copied()));
+    assert_eq!(max, TermProfile::max_of(profiles.iter().copied()));
+}
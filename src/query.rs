@@ -1,17 +1,35 @@
 use std::io;
-use std::time::Duration;
+#[cfg(unix)]
+use std::io::Read;
+use std::time::{Duration, Instant};
 
 use termina::escape::csi::{Csi, Device, Sgr};
 use termina::escape::dcs::{Dcs, DcsRequest, DcsResponse};
 use termina::style::{ColorSpec, RgbColor};
 use termina::{PlatformTerminal, Terminal};
 
-use crate::detect::DcsEvent;
+use crate::detect::{DcsEvent, ForcedTerminal};
 use crate::{
-    DUMB, DetectorSettings, EnvVarSource, IsTerminal, QueryTerminal, Rgb, SCREEN, TMUX, TTY_FORCE,
-    TermVar, prefix_or_equal,
+    DUMB, DetectorSettings, EnvVarSource, IsTerminal, MOSH_KEY, MOSH_SERVER_NETWORK_TMOUT,
+    NoTerminal, QueryTerminal, Rgb, SCREEN, TMUX, TTY_FORCE, TermVar, TermVars, prefix_or_equal,
 };
 
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_PROBE_COLOR: Rgb = Rgb {
+    red: 150,
+    green: 150,
+    blue: 150,
+};
+// Queries support by transmitting a 1x1 pixel image. The terminal responds without displaying it.
+// See https://sw.kovidgoyal.net/kitty/graphics-protocol/#querying-support-and-available-transmission-mediums
+const KITTY_GRAPHICS_QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct QueryResponse {
+    pub(crate) true_color: bool,
+    pub(crate) kitty_graphics: bool,
+}
+
 impl<T> DetectorSettings<T>
 where
     T: QueryTerminal,
@@ -26,6 +44,12 @@ where
         DetectorSettings {
             enable_terminfo: self.enable_terminfo,
             enable_tmux_info: self.enable_tmux_info,
+            background: self.background,
+            terminfo_override: self.terminfo_override,
+            total_budget: self.total_budget,
+            options: self.options,
+            force_is_terminal: self.force_is_terminal,
+            probe_color: self.probe_color,
             enable_query: true,
             query_terminal,
         }
@@ -40,20 +64,74 @@ impl DetectorSettings<DefaultTerminal> {
     /// threads from reading or writing to the terminal while this happens because they could
     /// conflict with each other.
     pub fn with_query() -> io::Result<Self> {
-        Ok(Self {
-            enable_query: true,
-            enable_terminfo: true,
-            enable_tmux_info: true,
-            query_terminal: DefaultTerminal::new()?,
-        })
+        Ok(DetectorSettings::new().query_terminal(DefaultTerminal::new()?))
+    }
+
+    /// Create a new [`DetectorSettings`] with every detection method enabled for the most
+    /// accurate result. This enables terminal querying, the terminfo database, tmux info, and
+    /// [`optimistic`](DetectorSettings::optimistic) detection.
+    ///
+    /// This may be slow since it waits on responses from the terminal and from external
+    /// processes such as `tmux`. The terminal may also be put into raw mode while the query is
+    /// performed and then back into cooked mode afterward, so other threads shouldn't read from
+    /// or write to the terminal at the same time.
+    pub fn thorough() -> io::Result<Self> {
+        Self::thorough_with(DefaultTerminal::new())
+    }
+}
+
+impl<Q> DetectorSettings<Q>
+where
+    Q: QueryTerminal,
+{
+    pub(crate) fn thorough_with(query_terminal: io::Result<Q>) -> io::Result<Self> {
+        // Terminfo and tmux info are already enabled by default
+        Ok(DetectorSettings::new()
+            .optimistic(true)
+            .query_terminal(query_terminal?))
+    }
+}
+
+impl DetectorSettings<Box<dyn QueryTerminal>> {
+    /// Create a new [`DetectorSettings`] with terminal querying enabled if possible.
+    ///
+    /// This is the same as [`with_query`](DetectorSettings::with_query), except terminal querying
+    /// is disabled instead of returning an error if the terminal can't be opened, such as on
+    /// systems without a controlling terminal.
+    pub fn with_query_or_fallback() -> Self {
+        Self::query_or_fallback(DefaultTerminal::new())
+    }
+
+    pub(crate) fn query_or_fallback<Q>(query_terminal: io::Result<Q>) -> Self
+    where
+        Q: QueryTerminal + 'static,
+    {
+        let (query_terminal, enable_query): (Box<dyn QueryTerminal>, bool) = match query_terminal {
+            Ok(query_terminal) => (Box::new(query_terminal), true),
+            Err(_) => (Box::new(NoTerminal), false),
+        };
+        let mut settings = DetectorSettings::new().query_terminal(query_terminal);
+        settings.enable_query = enable_query;
+        settings
     }
 }
 
 /// Default terminal querying implementation that queries `stdout`.
+///
+/// On Unix, responses are read directly from the controlling terminal so that secondary device
+/// attributes (DA2) and Kitty graphics protocol responses can be decoded as well. These are not
+/// requested on other platforms since the underlying terminal library doesn't parse the
+/// responses.
 #[derive(Debug)]
 pub struct DefaultTerminal {
     terminal: PlatformTerminal,
+    #[cfg(unix)]
+    input: std::fs::File,
+    #[cfg(unix)]
+    buffer: Vec<u8>,
     timeout: Duration,
+    total_timeout: Duration,
+    deadline: Option<Instant>,
 }
 
 impl DefaultTerminal {
@@ -61,16 +139,36 @@ impl DefaultTerminal {
     pub fn new() -> io::Result<Self> {
         Ok(Self {
             terminal: PlatformTerminal::new()?,
+            #[cfg(unix)]
+            input: std::fs::File::open("/dev/tty")?,
+            #[cfg(unix)]
+            buffer: Vec::new(),
             timeout: Duration::from_millis(100),
+            total_timeout: DEFAULT_QUERY_TIMEOUT,
+            deadline: None,
         })
     }
 
-    /// Sets the timeout for reading events from the terminal.
+    /// Verifies that the terminal is able to display the given color. See [`verify_color`] for
+    /// details.
+    pub fn verify_color(&mut self, color: Rgb) -> io::Result<bool> {
+        verify_color(self, color)
+    }
+
+    /// Sets the timeout for reading each event from the terminal.
     /// The default value is 100ms.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
+
+    /// Sets the maximum amount of time to spend reading events for a single query. This bounds
+    /// the query even if the terminal keeps sending unrelated events that reset the per-event
+    /// [`timeout`](Self::timeout). The default value is 1 second.
+    pub fn total_timeout(mut self, total_timeout: Duration) -> Self {
+        self.total_timeout = total_timeout;
+        self
+    }
 }
 
 impl io::Write for DefaultTerminal {
@@ -85,6 +183,9 @@ impl io::Write for DefaultTerminal {
 
 impl QueryTerminal for DefaultTerminal {
     fn setup(&mut self) -> io::Result<()> {
+        self.deadline = Some(Instant::now() + self.total_timeout);
+        #[cfg(unix)]
+        self.buffer.clear();
         self.terminal.enter_raw_mode()
     }
 
@@ -92,38 +193,236 @@ impl QueryTerminal for DefaultTerminal {
         self.terminal.enter_cooked_mode()
     }
 
+    #[cfg(unix)]
     fn read_event(&mut self) -> io::Result<DcsEvent> {
-        if !self
-            .terminal
-            .poll(termina::Event::is_escape, self.timeout.into())?
-        {
+        loop {
+            if let Some((len, event)) = parse_response(&self.buffer) {
+                self.buffer.drain(..len);
+                return Ok(event);
+            }
+            let remaining = self.remaining();
+            if remaining.is_zero() || !poll_input(&self.input, self.timeout.min(remaining))? {
+                return Ok(DcsEvent::TimedOut);
+            }
+            let mut buf = [0; 1024];
+            let read = self.input.read(&mut buf)?;
+            if read == 0 {
+                return Ok(DcsEvent::TimedOut);
+            }
+            self.buffer.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn read_event(&mut self) -> io::Result<DcsEvent> {
+        let remaining = self.remaining();
+        if remaining.is_zero() {
+            return Ok(DcsEvent::TimedOut);
+        }
+        if !self.terminal.poll(
+            termina::Event::is_escape,
+            self.timeout.min(remaining).into(),
+        )? {
             return Ok(DcsEvent::TimedOut);
         }
         let event = self.terminal.read(termina::Event::is_escape)?;
-        Ok(match event {
-            termina::Event::Dcs(Dcs::Response {
-                value: DcsResponse::GraphicRendition(sgrs),
-                ..
-            }) => sgrs
+        Ok(convert_event(event))
+    }
+
+    fn query_secondary_attributes(&self) -> bool {
+        cfg!(unix)
+    }
+
+    fn query_kitty_graphics(&self) -> bool {
+        cfg!(unix)
+    }
+}
+
+impl DefaultTerminal {
+    fn remaining(&self) -> Duration {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+            .unwrap_or(self.total_timeout)
+    }
+}
+
+#[cfg(unix)]
+fn poll_input(input: &std::fs::File, timeout: Duration) -> io::Result<bool> {
+    use rustix::event::{PollFd, PollFlags, Timespec};
+
+    let timeout = Timespec::try_from(timeout).map_err(io::Error::other)?;
+    let mut fds = [PollFd::new(input, PollFlags::IN)];
+    loop {
+        match rustix::event::poll(&mut fds, Some(&timeout)) {
+            Ok(ready) => return Ok(ready > 0),
+            Err(rustix::io::Errno::INTR) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+// Splits the next response off of the input buffer. Returns the length of the response along
+// with the parsed event, or None if the buffer doesn't contain a complete response yet.
+#[cfg(unix)]
+fn parse_response(buffer: &[u8]) -> Option<(usize, DcsEvent)> {
+    const ESC: u8 = 0x1b;
+    const BEL: u8 = 0x07;
+
+    let first = *buffer.first()?;
+    if first != ESC {
+        // Skip anything that isn't an escape sequence, such as keys that were pressed during the
+        // query
+        let len = buffer
+            .iter()
+            .position(|b| *b == ESC)
+            .unwrap_or(buffer.len());
+        return Some((len, DcsEvent::Other));
+    }
+    let len = match *buffer.get(1)? {
+        b'[' => buffer[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 3,
+        // String sequences are terminated by ST (ESC \) or BEL
+        b'P' | b']' | b'_' => {
+            let end = buffer[2..]
                 .iter()
-                .find_map(|s| {
-                    if let Sgr::Background(ColorSpec::TrueColor(rgb)) = s {
-                        DcsEvent::BackgroundColor(Rgb {
-                            red: rgb.red,
-                            green: rgb.green,
-                            blue: rgb.blue,
-                        })
-                        .into()
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or(DcsEvent::Other),
-            termina::Event::Csi(Csi::Device(Device::DeviceAttributes(()))) => {
-                DcsEvent::DeviceAttributes
-            }
-            _ => DcsEvent::Other,
-        })
+                .enumerate()
+                .position(|(i, b)| *b == BEL || (*b == b'\\' && buffer[i + 1] == ESC))?;
+            end + 3
+        }
+        // Don't consume the start of the next sequence
+        ESC => 1,
+        _ => 2,
+    };
+    let sequence = &buffer[..len];
+    if let Some(attributes) = sequence
+        .strip_prefix(b"\x1b[>")
+        .and_then(|s| s.strip_suffix(b"c"))
+    {
+        return Some((len, parse_secondary_attributes(attributes)));
+    }
+    if let Some(color) = sequence.strip_prefix(b"\x1b]4;") {
+        let color = color
+            .strip_suffix(b"\x1b\\")
+            .or_else(|| color.strip_suffix(&[BEL]))
+            .unwrap_or(color);
+        return Some((len, parse_palette_color(color)));
+    }
+    if let Some(graphics) = sequence.strip_prefix(b"\x1b_G") {
+        // The response contains the query's parameters followed by "OK" or an error message
+        let supported = graphics
+            .split(|b| *b == b';')
+            .nth(1)
+            .is_some_and(|status| status.starts_with(b"OK"));
+        return Some((len, DcsEvent::KittyGraphics(supported)));
+    }
+    let mut parser = termina::Parser::default();
+    parser.parse(sequence, false);
+    Some((
+        len,
+        parser.pop().map(convert_event).unwrap_or(DcsEvent::Other),
+    ))
+}
+
+// Parses the parameters of a DA2 response in the form `kind;version;rom`
+#[cfg(unix)]
+fn parse_secondary_attributes(attributes: &[u8]) -> DcsEvent {
+    let attributes = String::from_utf8_lossy(attributes);
+    let mut params = attributes.split(';');
+    let kind = params.next().and_then(|p| p.parse().ok());
+    let version = params.next().and_then(|p| p.parse().ok());
+    match (kind, version) {
+        (Some(kind), Some(version)) => DcsEvent::SecondaryAttributes { kind, version },
+        _ => DcsEvent::Other,
+    }
+}
+
+// Parses the parameters of an OSC 4 response in the form `index;rgb:rrrr/gggg/bbbb`
+#[cfg(unix)]
+fn parse_palette_color(color: &[u8]) -> DcsEvent {
+    let color = String::from_utf8_lossy(color);
+    let Some((index, rgb)) = color.split_once(';') else {
+        return DcsEvent::Other;
+    };
+    match (index.parse(), rgb.parse::<RgbColor>()) {
+        (Ok(index), Ok(rgb)) => DcsEvent::PaletteColor {
+            index,
+            rgb: Rgb {
+                red: rgb.red,
+                green: rgb.green,
+                blue: rgb.blue,
+            },
+        },
+        _ => DcsEvent::Other,
+    }
+}
+
+fn convert_event(event: termina::Event) -> DcsEvent {
+    match event {
+        termina::Event::Dcs(Dcs::Response {
+            value: DcsResponse::GraphicRendition(sgrs),
+            ..
+        }) => sgrs
+            .iter()
+            .find_map(|s| {
+                if let Sgr::Background(ColorSpec::TrueColor(rgb)) = s {
+                    DcsEvent::BackgroundColor(Rgb {
+                        red: rgb.red,
+                        green: rgb.green,
+                        blue: rgb.blue,
+                    })
+                    .into()
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(DcsEvent::Other),
+        termina::Event::Csi(Csi::Device(Device::DeviceAttributes(()))) => {
+            DcsEvent::DeviceAttributes
+        }
+        _ => DcsEvent::Other,
+    }
+}
+
+impl TermVars {
+    /// Re-runs only the terminal query and updates [`TermMetaVars::dcs_response`] and
+    /// [`TermMetaVars::kitty_graphics`] with the result. If a background color is set in the
+    /// settings, [`TermMetaVars::background`] is updated as well.
+    ///
+    /// This is useful if the variables were loaded without querying the terminal, such as when
+    /// the terminal wasn't available yet, since the rest of the variables don't need to be
+    /// loaded again. The variables are left unchanged if the query fails.
+    ///
+    /// [`TermMetaVars::dcs_response`]: crate::TermMetaVars::dcs_response
+    /// [`TermMetaVars::kitty_graphics`]: crate::TermMetaVars::kitty_graphics
+    /// [`TermMetaVars::background`]: crate::TermMetaVars::background
+    pub fn refresh_dcs<S, T, Q>(
+        &mut self,
+        source: &S,
+        out: &T,
+        mut settings: DetectorSettings<Q>,
+    ) -> io::Result<()>
+    where
+        S: EnvVarSource,
+        T: IsTerminal,
+        Q: QueryTerminal,
+    {
+        let out = ForcedTerminal {
+            out,
+            is_terminal: settings.force_is_terminal,
+        };
+        let response = query_detect(
+            source,
+            &out,
+            &mut settings.query_terminal,
+            &self.meta.term.value(),
+            settings.total_budget,
+            settings.probe_color,
+        )?;
+        self.meta.dcs_response = response.true_color;
+        self.meta.kitty_graphics = response.kitty_graphics;
+        if let Some(background) = settings.background {
+            self.meta.background = Some(background);
+        }
+        Ok(())
     }
 }
 
@@ -132,63 +431,238 @@ pub(crate) fn query_detect<S, Q, T>(
     out: &T,
     query_terminal: &mut Q,
     term: &str,
-) -> io::Result<bool>
+    budget: Option<Duration>,
+    probe_color: Option<Rgb>,
+) -> io::Result<QueryResponse>
 where
     S: EnvVarSource,
     Q: QueryTerminal,
     T: IsTerminal,
 {
-    const TEST_COLOR: Rgb = Rgb {
-        red: 150,
-        green: 150,
-        blue: 150,
-    };
     let tty_force = TermVar::from_source(source, TTY_FORCE);
-    // Screen and tmux don't support this sequence
+    // Screen and tmux don't support this sequence. Mosh doesn't forward the response, so the query
+    // would wait until it times out.
     if (!out.is_terminal() && !tty_force.is_truthy())
         || term == DUMB
         || prefix_or_equal(term, TMUX)
         || !TermVar::from_source(source, &TMUX.to_ascii_uppercase()).is_empty()
         || prefix_or_equal(term, SCREEN)
+        || !TermVar::from_source(source, MOSH_KEY).is_empty()
+        || !TermVar::from_source(source, MOSH_SERVER_NETWORK_TMOUT).is_empty()
     {
-        return Ok(false);
+        return Ok(QueryResponse::default());
     }
 
+    let probe_color = probe_color.unwrap_or(DEFAULT_PROBE_COLOR);
+    let mut true_color = false;
+    let mut background_read = false;
+    let mut secondary_true_color = false;
+    let mut kitty_graphics = false;
+    let completed = run_query(
+        query_terminal,
+        budget,
+        |query_terminal| {
+            write_background_request(query_terminal, probe_color)?;
+            if query_terminal.query_secondary_attributes() {
+                write!(
+                    query_terminal,
+                    "{}",
+                    Csi::Device(Device::RequestSecondaryDeviceAttributes)
+                )?;
+            }
+            if query_terminal.query_kitty_graphics() {
+                write!(query_terminal, "{KITTY_GRAPHICS_QUERY}")?;
+            }
+            Ok(())
+        },
+        |event| match event {
+            DcsEvent::BackgroundColor(rgb) => {
+                true_color = rgb == probe_color;
+                background_read = true;
+            }
+            DcsEvent::SecondaryAttributes { kind, version } => {
+                secondary_true_color = secondary_attributes_true_color(kind, version);
+            }
+            DcsEvent::KittyGraphics(supported) => {
+                kitty_graphics = supported;
+            }
+            _ => {}
+        },
+    )?;
+    if !completed {
+        return Ok(QueryResponse::default());
+    }
+    Ok(QueryResponse {
+        // The background round trip is more reliable, DA2 is only used as a fallback if the
+        // terminal didn't respond to it
+        true_color: if background_read {
+            true_color
+        } else {
+            secondary_true_color
+        },
+        kitty_graphics,
+    })
+}
+
+/// Sets the background to the given color and reads it back from the terminal to verify that
+/// the terminal is able to display it. This can be used as a diagnostic to check if a color
+/// will render as expected.
+///
+/// Returns `false` if the terminal reports a different color or doesn't respond before timing
+/// out.
+pub fn verify_color<Q>(query_terminal: &mut Q, color: Rgb) -> io::Result<bool>
+where
+    Q: QueryTerminal,
+{
+    let mut matches = false;
+    let completed = run_query(
+        query_terminal,
+        None,
+        |query_terminal| write_background_request(query_terminal, color),
+        |event| {
+            if let DcsEvent::BackgroundColor(rgb) = event {
+                matches = rgb == color;
+            }
+        },
+    )?;
+    Ok(completed && matches)
+}
+
+/// Queries the terminal for Kitty graphics protocol support.
+///
+/// Returns `false` if the terminal doesn't respond to the query before the device attributes
+/// response. The query stops once the given timeout elapses, even if the terminal keeps sending
+/// unrelated events. The query terminal must be able to parse the response into
+/// [`DcsEvent::KittyGraphics`]. [`DefaultTerminal`] supports this on Unix. Other platforms
+/// require a custom [`QueryTerminal`] since the underlying terminal library doesn't parse the
+/// response.
+pub fn query_kitty_graphics<Q>(query_terminal: &mut Q, timeout: Duration) -> io::Result<bool>
+where
+    Q: QueryTerminal,
+{
+    let mut supported = false;
+    run_query(
+        query_terminal,
+        Some(timeout),
+        |query_terminal| write!(query_terminal, "{KITTY_GRAPHICS_QUERY}"),
+        |event| {
+            if let DcsEvent::KittyGraphics(response) = event {
+                supported = response;
+            }
+        },
+    )?;
+    Ok(supported)
+}
+
+/// Queries the terminal for the colors of the given palette indices using OSC 4.
+///
+/// Indices that the terminal doesn't respond to are omitted from the result. The query stops
+/// once the given timeout elapses, even if the terminal keeps sending unrelated events. The
+/// query terminal must be able to parse OSC 4 responses into [`DcsEvent::PaletteColor`].
+/// [`DefaultTerminal`] supports this on Unix. Other platforms require a custom
+/// [`QueryTerminal`] since the underlying terminal library doesn't parse the response.
+pub fn query_palette<Q>(
+    query_terminal: &mut Q,
+    indices: &[u8],
+    timeout: Duration,
+) -> io::Result<Vec<(u8, Rgb)>>
+where
+    Q: QueryTerminal,
+{
+    let mut colors = Vec::with_capacity(indices.len());
+    run_query(
+        query_terminal,
+        Some(timeout),
+        |query_terminal| {
+            for index in indices {
+                write!(query_terminal, "\x1b]4;{index};?\x1b\\")?;
+            }
+            Ok(())
+        },
+        |event| {
+            if let DcsEvent::PaletteColor { index, rgb } = event
+                && indices.contains(&index)
+            {
+                colors.push((index, rgb));
+            }
+        },
+    )?;
+    Ok(colors)
+}
+
+// Sends the requests followed by a primary device attributes request, then passes each response
+// to on_event until the device attributes response marks the end of the query. Returns false if
+// the query timed out or the budget was exceeded first. The terminal is cleaned up even if the
+// query fails.
+fn run_query<Q, W, E>(
+    query_terminal: &mut Q,
+    budget: Option<Duration>,
+    write_requests: W,
+    mut on_event: E,
+) -> io::Result<bool>
+where
+    Q: QueryTerminal,
+    W: FnOnce(&mut Q) -> io::Result<()>,
+    E: FnMut(DcsEvent),
+{
+    let start = Instant::now();
     query_terminal.setup()?;
+    let read_responses = || -> io::Result<bool> {
+        write_requests(query_terminal)?;
+        // Primary device attributes must be last since it's used to detect the end of the query
+        write!(
+            query_terminal,
+            "{}",
+            Csi::Device(Device::RequestPrimaryDeviceAttributes)
+        )?;
+        query_terminal.flush()?;
+        loop {
+            if budget.is_some_and(|budget| start.elapsed() >= budget) {
+                return Ok(false);
+            }
+            match query_terminal.read_event()? {
+                DcsEvent::TimedOut => return Ok(false),
+                DcsEvent::DeviceAttributes => return Ok(true),
+                event => on_event(event),
+            }
+        }
+    };
+    let result = read_responses();
+    let cleanup = query_terminal.cleanup();
+    let completed = result?;
+    cleanup?;
+    Ok(completed)
+}
+
+fn write_background_request<Q>(query_terminal: &mut Q, color: Rgb) -> io::Result<()>
+where
+    Q: QueryTerminal,
+{
     write!(
         query_terminal,
-        "{}{}{}{}",
+        "{}{}{}",
         Csi::Sgr(Sgr::Background(ColorSpec::TrueColor(
             RgbColor {
-                red: TEST_COLOR.red,
-                green: TEST_COLOR.green,
-                blue: TEST_COLOR.blue
+                red: color.red,
+                green: color.green,
+                blue: color.blue
             }
             .into()
         ))),
         Dcs::Request(DcsRequest::GraphicRendition),
         Csi::Sgr(Sgr::Reset),
-        Csi::Device(Device::RequestPrimaryDeviceAttributes),
-    )?;
-    query_terminal.flush()?;
-
-    let mut true_color = false;
-    loop {
-        let event = query_terminal.read_event()?;
+    )
+}
 
-        match event {
-            DcsEvent::TimedOut => {
-                return Ok(false);
-            }
-            DcsEvent::BackgroundColor(rgb) => {
-                true_color = rgb == TEST_COLOR;
-            }
-            DcsEvent::DeviceAttributes => {
-                break;
-            }
-            DcsEvent::Other => {}
-        }
-    }
-    query_terminal.cleanup()?;
-    Ok(true_color)
+fn secondary_attributes_true_color(kind: u16, version: u32) -> bool {
+    // Other terminals commonly report xterm's identifier, but with a version number that's
+    // unrelated to xterm's patch number, so this may only detect a subset of capable terminals.
+    const XTERM: u16 = 41;
+    // Direct color support was added in xterm patch 331
+    const XTERM_TRUECOLOR_VERSION: u32 = 331;
+    kind == XTERM && version >= XTERM_TRUECOLOR_VERSION
 }
+
+#[cfg(all(test, unix))]
+#[path = "./query_test.rs"]
+mod query_test;
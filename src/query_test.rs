@@ -0,0 +1,98 @@
+use rstest::rstest;
+
+use super::parse_response;
+use crate::{DcsEvent, Rgb};
+
+fn parse_all(input: &[u8]) -> Vec<DcsEvent> {
+    let mut buffer = input.to_vec();
+    let mut events = Vec::new();
+    while let Some((len, event)) = parse_response(&buffer) {
+        buffer.drain(..len);
+        events.push(event);
+    }
+    assert!(buffer.is_empty());
+    events
+}
+
+#[rstest]
+#[case(b"\x1b[>41;351;0c", 41, 351)]
+#[case(b"\x1b[>1;4000;15c", 1, 4000)]
+#[case(b"\x1b[>0;10;1c", 0, 10)]
+fn parse_secondary_attributes(#[case] input: &[u8], #[case] kind: u16, #[case] version: u32) {
+    let events = parse_all(input);
+    assert!(matches!(
+        events.as_slice(),
+        [DcsEvent::SecondaryAttributes { kind: k, version: v }] if *k == kind && *v == version
+    ));
+}
+
+#[test]
+fn parse_full_response() {
+    let events = parse_all(
+        b"\x1bP1$r0;48:2::150:150:150m\x1b\\\x1b[>41;351;0c\x1b[?64;1;2;6;9;15;18;21;22c",
+    );
+    assert!(matches!(
+        events.as_slice(),
+        [
+            DcsEvent::BackgroundColor(rgb),
+            DcsEvent::SecondaryAttributes {
+                kind: 41,
+                version: 351
+            },
+            DcsEvent::DeviceAttributes,
+        ] if *rgb == Rgb::from((150, 150, 150))
+    ));
+}
+
+#[test]
+fn parse_skips_unrelated_input() {
+    let events = parse_all(b"abc\x1b[>41;351;0c");
+    assert!(matches!(
+        events.as_slice(),
+        [DcsEvent::Other, DcsEvent::SecondaryAttributes { .. }]
+    ));
+}
+
+#[rstest]
+#[case(b"\x1b]4;1;rgb:cdcd/0000/0000\x1b\\", 1, (205, 0, 0))]
+#[case(b"\x1b]4;15;rgb:ff/ff/ff\x07", 15, (255, 255, 255))]
+#[case(b"\x1b]4;255;rgb:eeee/eeee/eeee\x1b\\", 255, (238, 238, 238))]
+fn parse_palette_color(#[case] input: &[u8], #[case] index: u8, #[case] rgb: (u8, u8, u8)) {
+    let events = parse_all(input);
+    assert!(matches!(
+        events.as_slice(),
+        [DcsEvent::PaletteColor { index: i, rgb: c }] if *i == index && *c == Rgb::from(rgb)
+    ));
+}
+
+#[rstest]
+#[case(b"\x1b]4;256;rgb:ffff/ffff/ffff\x1b\\")]
+#[case(b"\x1b]4;1;?\x1b\\")]
+#[case(b"\x1b]4;1\x1b\\")]
+fn parse_palette_color_invalid(#[case] input: &[u8]) {
+    assert!(matches!(parse_all(input).as_slice(), [DcsEvent::Other]));
+}
+
+#[rstest]
+#[case(b"\x1b_Gi=31;OK\x1b\\", true)]
+#[case(b"\x1b_Gi=31;ENOENT:Unsupported transmission medium\x1b\\", false)]
+#[case(b"\x1b_Gi=31;EINVAL:Unsupported format\x1b\\", false)]
+fn parse_kitty_graphics(#[case] input: &[u8], #[case] expected: bool) {
+    let events = parse_all(input);
+    assert!(matches!(
+        events.as_slice(),
+        [DcsEvent::KittyGraphics(supported)] if *supported == expected
+    ));
+}
+
+#[rstest]
+#[case(b"")]
+#[case(b"\x1b")]
+#[case(b"\x1b[>41;351")]
+#[case(b"\x1bP1$r48:2::150:150:150m")]
+#[case(b"\x1bP1$r48:2::150:150:150m\x1b")]
+#[case(b"\x1b]4;1;rgb:cdcd/0000/0000")]
+#[case(b"\x1b_Gi=31;OK")]
+fn parse_incomplete(#[case] input: &[u8]) {
+    assert!(parse_response(input).is_none());
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "convert-anstyle")]
+
+use std::fmt::Write;
+
+use termprofile::anstyle::{Ansi256Color, RgbColor};
+use termprofile::{ansi256_to_ansi16, ansi256_to_rgb, rgb_to_ansi256};
+
+// The conversion tables are stored in a separate file so any change to them shows up in the diff.
+// Run with UPDATE_SNAPSHOTS=1 to regenerate the file after an intentional change.
+const SNAPSHOT: &str = include_str!("./conversion_tables.txt");
+const SNAPSHOT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/conversion_tables.txt");
+
+// Coarse enough to keep the snapshot small, but fine enough to cover each quantization interval
+const RGB_STEP: usize = 17;
+
+#[test]
+fn conversion_tables() {
+    let actual = render_tables();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(SNAPSHOT_PATH, &actual).expect("failed to write snapshot");
+        return;
+    }
+    let failures: Vec<_> = SNAPSHOT
+        .lines()
+        .zip(actual.lines())
+        .filter(|(expected, actual)| expected != actual)
+        .map(|(expected, actual)| format!("expected {expected:?}, got {actual:?}"))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    assert_eq!(
+        SNAPSHOT.lines().count(),
+        actual.lines().count(),
+        "snapshot length changed"
+    );
+}
+
+fn render_tables() -> String {
+    let mut out = String::new();
+    out.push_str(
+        "# Snapshot of the color conversion tables. Regenerate with UPDATE_SNAPSHOTS=1.\n",
+    );
+    out.push_str("#\n");
+    out.push_str("# [ansi256] lines are `<index> <rgb> <ansi16 index>`\n");
+    out.push_str("# [rgb_to_ansi256] lines are `<rgb> <ansi256 index>`\n");
+
+    out.push_str("\n[ansi256]\n");
+    for index in 0..=255 {
+        let rgb = ansi256_to_rgb(Ansi256Color(index));
+        let ansi16 = ansi256_to_ansi16(index) as u8;
+        writeln!(out, "{index} {} {ansi16}", hex(rgb)).expect("write failed");
+    }
+
+    out.push_str("\n[rgb_to_ansi256]\n");
+    for r in (0..=255).step_by(RGB_STEP) {
+        for g in (0..=255).step_by(RGB_STEP) {
+            for b in (0..=255).step_by(RGB_STEP) {
+                let rgb = RgbColor(r, g, b);
+                writeln!(out, "{} {}", hex(rgb), rgb_to_ansi256(rgb)).expect("write failed");
+            }
+        }
+    }
+    out
+}
+
+fn hex(rgb: RgbColor) -> String {
+    format!("{:02x}{:02x}{:02x}", rgb.r(), rgb.g(), rgb.b())
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use termprofile::{DetectorSettings, IsTerminal, TermProfile, TermVars, WindowsVars};
+
+// Captures are stored in a separate file so new cases from bug reports can be added without
+// writing any code. See the header in the file for the format.
+const CAPTURES: &str = include_str!("./env_captures.txt");
+
+#[derive(Debug, Default)]
+struct Capture {
+    name: String,
+    tty: bool,
+    expected: Option<TermProfile>,
+    windows: Option<(u64, u64)>,
+    tmux_info: Vec<String>,
+    env: HashMap<String, String>,
+}
+
+struct Output {
+    tty: bool,
+}
+
+impl IsTerminal for Output {
+    fn is_terminal(&self) -> bool {
+        self.tty
+    }
+}
+
+#[test]
+fn env_captures() {
+    let captures = parse_captures(CAPTURES);
+    assert!(!captures.is_empty());
+
+    let failures: Vec<_> = captures
+        .iter()
+        .filter_map(|capture| {
+            let expected = capture
+                .expected
+                .unwrap_or_else(|| panic!("{}: missing @expected", capture.name));
+            let actual = TermProfile::detect_with_vars(load_vars(capture));
+            (actual != expected)
+                .then(|| format!("{}: expected {expected:?}, got {actual:?}", capture.name))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+fn load_vars(capture: &Capture) -> TermVars {
+    let settings = DetectorSettings::new().enable_tmux_info(false);
+    #[cfg(feature = "terminfo")]
+    let settings = settings.enable_terminfo(false);
+
+    let mut vars = TermVars::from_source(&capture.env, &Output { tty: capture.tty }, settings);
+    vars.windows = WindowsVars::default();
+    if let Some((os_version, build_number)) = capture.windows {
+        vars.windows.is_windows = true;
+        vars.windows.os_version = os_version;
+        vars.windows.build_number = build_number;
+    }
+    vars.tmux.tmux_info = capture.tmux_info.join("\n");
+    vars
+}
+
+fn parse_captures(text: &str) -> Vec<Capture> {
+    let mut captures = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            captures.push(Capture {
+                name: name.to_string(),
+                tty: true,
+                ..Default::default()
+            });
+            continue;
+        }
+        let capture = captures
+            .last_mut()
+            .unwrap_or_else(|| panic!("variable outside of a capture: {line}"));
+        let (key, value) = line
+            .split_once('=')
+            .unwrap_or_else(|| panic!("{}: invalid line: {line}", capture.name));
+        match key {
+            "@tty" => capture.tty = value.parse().expect("invalid @tty"),
+            "@expected" => capture.expected = Some(parse_profile(value)),
+            "@windows" => capture.windows = Some(parse_windows_version(value)),
+            "@tmux_info" => capture.tmux_info.push(value.to_string()),
+            key if key.starts_with('@') => panic!("{}: unknown key {key}", capture.name),
+            key => {
+                capture.env.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    captures
+}
+
+fn parse_profile(profile: &str) -> TermProfile {
+    match profile {
+        "NoTty" => TermProfile::NoTty,
+        "NoColor" => TermProfile::NoColor,
+        "Ansi8" => TermProfile::Ansi8,
+        "Ansi16" => TermProfile::Ansi16,
+        "Ansi256" => TermProfile::Ansi256,
+        "TrueColor" => TermProfile::TrueColor,
+        _ => panic!("invalid profile: {profile}"),
+    }
+}
+
+fn parse_windows_version(version: &str) -> (u64, u64) {
+    let parts: Vec<u64> = version
+        .split('.')
+        .map(|p| p.parse().expect("invalid @windows"))
+        .collect();
+    match parts.as_slice() {
+        [os_version, _, build_number] => (*os_version, *build_number),
+        _ => panic!("invalid @windows: {version}"),
+    }
+}